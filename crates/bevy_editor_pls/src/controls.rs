@@ -1,6 +1,7 @@
 use transform_gizmo_bevy::GizmoMode;
 use bevy::{prelude::*, utils::HashMap};
 use bevy_editor_pls_core::{editor_window::EditorWindow, Editor, EditorEvent};
+use bevy_editor_pls_default_windows::history::HistoryEvent;
 
 
 
@@ -151,6 +152,8 @@ pub enum Action {
     FocusSelected,
     ClearSelection,
     DeleteSelectedEntities, 
+    Undo,
+    Redo,
 
     #[cfg(feature = "default_windows")]
     SetGizmoModeTranslate,
@@ -168,6 +171,8 @@ impl std::fmt::Display for Action {
             Action::FocusSelected => write!(f, "Focus Selected Entity"),
             Action::DeleteSelectedEntities => write!(f, "Delete Selected Entities"), 
             Action::ClearSelection => write!(f, "Clear Selection"),
+            Action::Undo => write!(f, "Undo"),
+            Action::Redo => write!(f, "Redo"),
             #[cfg(feature = "default_windows")]
             Action::SetGizmoModeTranslate => write!(f, "Activate translation gizmo"),
             #[cfg(feature = "default_windows")]
@@ -232,6 +237,7 @@ pub fn editor_controls_system(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     mut editor_events: EventWriter<EditorEvent>,
+    mut history_events: EventWriter<HistoryEvent>,
     mut editor: ResMut<Editor>,
 ) {
     if controls.just_pressed(
@@ -280,6 +286,13 @@ pub fn editor_controls_system(
 
 
 
+    //the redo chord also contains the undo chord
+    if controls.just_pressed(Action::Redo, &keyboard_input, &mouse_input, &editor) {
+        history_events.send(HistoryEvent::Redo);
+    } else if controls.just_pressed(Action::Undo, &keyboard_input, &mouse_input, &editor) {
+        history_events.send(HistoryEvent::Undo);
+    }
+
     if controls.just_pressed(
         Action::ClearSelection,
         &keyboard_input,
@@ -338,6 +351,7 @@ impl EditorControls {
     /// - `C-Enter`: pause time
     /// - `E`: toggle editor
     /// - `F`: focus on selected entity
+    /// - `C-Z` / `C-S-Z`: undo / redo
     /// `T/R/S`: show translate/rotate/scale gizmo
     pub fn default_bindings() -> Self {
         let mut controls = EditorControls::default();
@@ -370,6 +384,29 @@ impl EditorControls {
         );
 
 
+        controls.insert(
+            Action::Undo,
+            Binding {
+                input: UserInput::Chord(vec![
+                    Button::Keyboard(KeyCode::ControlLeft),
+                    Button::Keyboard(KeyCode::KeyZ),
+                ]),
+                conditions: vec![BindingCondition::ListeningForText(false)],
+            },
+        );
+
+        controls.insert(
+            Action::Redo,
+            Binding {
+                input: UserInput::Chord(vec![
+                    Button::Keyboard(KeyCode::ControlLeft),
+                    Button::Keyboard(KeyCode::ShiftLeft),
+                    Button::Keyboard(KeyCode::KeyZ),
+                ]),
+                conditions: vec![BindingCondition::ListeningForText(false)],
+            },
+        );
+
         controls.insert(
             Action::FocusSelected,
             Binding {
@@ -471,6 +508,8 @@ impl EditorWindow for ControlsWindow {
             Action::PlayPauseEditor,
             Action::PauseUnpauseTime,
             Action::FocusSelected,
+            Action::Undo,
+            Action::Redo,
         ] {
             ui.label(egui::RichText::new(action.to_string()).strong());
             let bindings = controls.get(action);
@@ -135,11 +135,14 @@ impl Plugin for EditorPlugin {
             use bevy_editor_pls_default_windows::lighting::LightingWindow;
 
             use bevy_editor_pls_default_windows::doodads::DoodadsWindow;
+            use bevy_editor_pls_default_windows::doodads::doodad_query::DoodadQueryWindow;
             use bevy_editor_pls_default_windows::zones::ZoneWindow;
+            use bevy_editor_pls_default_windows::history::HistoryWindow;
 
             app.add_editor_window::<HierarchyWindow>();
             app.add_editor_window::<AssetsWindow>();
             app.add_editor_window::<DoodadsWindow>();
+            app.add_editor_window::<DoodadQueryWindow>();
             app.add_editor_window::<InspectorWindow>();
             app.add_editor_window::<DebugSettingsWindow>();
             app.add_editor_window::<AddWindow>();
@@ -149,6 +152,7 @@ impl Plugin for EditorPlugin {
             app.add_editor_window::<ResourcesWindow>();
             app.add_editor_window::<LightingWindow>();
             app.add_editor_window::<ZoneWindow>();
+            app.add_editor_window::<HistoryWindow>();
             app.add_editor_window::<GizmoWindow>();
             app.add_editor_window::<PlacementWindow>();
             app.add_editor_window::<controls::ControlsWindow>();
@@ -187,6 +191,7 @@ impl Plugin for EditorPlugin {
                 &[
                     std::any::TypeId::of::<HierarchyWindow>(),
                     std::any::TypeId::of::<DoodadsWindow>(),
+                    std::any::TypeId::of::<DoodadQueryWindow>(),
                 ],
             );
             let [_game, _bottom] = internal_state.split_many(
@@ -195,6 +200,7 @@ impl Plugin for EditorPlugin {
                 egui_dock::Split::Below,
                 &[
                     std::any::TypeId::of::<ZoneWindow>(),
+                    std::any::TypeId::of::<HistoryWindow>(),
                     std::any::TypeId::of::<ResourcesWindow>(),
                  //   std::any::TypeId::of::<AssetsWindow>(),
                     std::any::TypeId::of::<LightingWindow>(),
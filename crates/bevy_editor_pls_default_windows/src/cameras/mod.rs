@@ -249,3 +249,29 @@ fn set_main_pass_viewport(
         cam.viewport = viewport.clone();
     });
 }
+
+/// Moves the active editor camera back from the middle of the entities, keeping its rotation,
+/// far enough to see all of them.
+pub fn focus_editor_camera(world: &mut World, entities: &[Entity]) {
+    let positions: Vec<Vec3> = entities
+        .iter()
+        .filter_map(|entity| world.get::<GlobalTransform>(*entity))
+        .map(|global_transform| global_transform.translation())
+        .collect();
+
+    if positions.is_empty() {
+        return;
+    }
+
+    let center = positions.iter().copied().sum::<Vec3>() / positions.len() as f32;
+    let radius = positions
+        .iter()
+        .map(|position| position.distance(center))
+        .fold(2.0, f32::max);
+
+    let mut camera_query = world.query_filtered::<&mut Transform, With<ActiveEditorCamera>>();
+    for mut camera_transform in camera_query.iter_mut(world) {
+        let forward = camera_transform.forward();
+        camera_transform.translation = center - forward * (radius * 2.5 + 5.0);
+    }
+}
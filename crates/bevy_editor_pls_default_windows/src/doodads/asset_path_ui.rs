@@ -0,0 +1,187 @@
+use std::any::{Any, TypeId};
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy_inspector_egui::egui::{self, RichText};
+use bevy_inspector_egui::inspector_egui_impls::InspectorEguiImpl;
+use bevy_inspector_egui::reflect_inspector::InspectorUi;
+
+use crate::project::editor_project;
+use crate::zones::zone_file::CustomPropAssetPath;
+
+/*
+
+Asset path custom props are edited as a text field with a Browse popup listing the files in
+the project asset folder, and are flagged when no such file exists.
+
+The widget is registered as the inspector impl of CustomPropAssetPath, so it shows up wherever
+the reflect inspector draws a prop, including inside lists and maps.
+
+*/
+
+//the asset folder is scanned when the popup opens, not every frame
+#[derive(Clone, Default)]
+struct AssetPathBrowserState {
+    filter: String,
+    asset_files: Vec<String>,
+}
+
+/// Every file under the asset folder, as a path relative to it with forward slashes.
+fn asset_file_paths() -> Vec<String> {
+    let asset_folder = editor_project().asset_folder.clone();
+
+    let mut asset_files: Vec<String> = Vec::new();
+    let mut folders = vec![asset_folder.clone()];
+
+    while let Some(folder) = folders.pop() {
+        let Ok(entries) = fs::read_dir(&folder) else {
+            continue;
+        };
+
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            if path.is_dir() {
+                folders.push(path);
+            } else if let Ok(relative_path) = path.strip_prefix(&asset_folder) {
+                asset_files.push(relative_path.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+
+    asset_files.sort();
+    asset_files
+}
+
+pub fn asset_path_exists(asset_path: &str) -> bool {
+    //labels like `#Scene0` are not part of the file name
+    let file_path = asset_path.split('#').next().unwrap_or(asset_path);
+
+    editor_project().asset_folder.join(Path::new(file_path)).is_file()
+}
+
+/// Text field, Browse popup and existence check for a path relative to the asset folder.
+pub fn asset_path_ui(asset_path: &mut String, id: egui::Id, ui: &mut egui::Ui) -> bool {
+    let mut changed = false;
+    let popup_id = id.with("asset_path_browser");
+
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            changed |= ui.text_edit_singleline(asset_path).changed();
+
+            let browse_response = ui.button("Browse");
+            if browse_response.clicked() {
+                let state = AssetPathBrowserState {
+                    filter: String::new(),
+                    asset_files: asset_file_paths(),
+                };
+                ui.data_mut(|data| data.insert_temp(popup_id, state));
+                ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+            }
+
+            egui::popup_below_widget(
+                ui,
+                popup_id,
+                &browse_response,
+                egui::PopupCloseBehavior::CloseOnClickOutside,
+                |ui| {
+                    ui.set_min_width(300.0);
+
+                    let mut state: AssetPathBrowserState =
+                        ui.data(|data| data.get_temp(popup_id)).unwrap_or_default();
+
+                    ui.horizontal(|ui| {
+                        ui.label("Filter:");
+                        ui.text_edit_singleline(&mut state.filter);
+                    });
+
+                    let filter = state.filter.to_lowercase();
+
+                    egui::ScrollArea::vertical()
+                        .max_height(240.0)
+                        .show(ui, |ui| {
+                            for asset_file in state
+                                .asset_files
+                                .iter()
+                                .filter(|asset_file| asset_file.to_lowercase().contains(&filter))
+                            {
+                                if ui.selectable_label(asset_file == asset_path, asset_file).clicked() {
+                                    *asset_path = asset_file.clone();
+                                    changed = true;
+                                    ui.memory_mut(|memory| memory.close_popup());
+                                }
+                            }
+                        });
+
+                    ui.data_mut(|data| data.insert_temp(popup_id, state));
+                },
+            );
+        });
+
+        if !asset_path.is_empty() && !asset_path_exists(asset_path) {
+            ui.label(
+                RichText::new(format!(
+                    "{} not found in {:?}",
+                    asset_path,
+                    editor_project().asset_folder
+                ))
+                .color(egui::Color32::RED),
+            );
+        }
+    });
+
+    changed
+}
+
+fn asset_path_inspector_ui(
+    value: &mut dyn Any,
+    ui: &mut egui::Ui,
+    _options: &dyn Any,
+    id: egui::Id,
+    _env: InspectorUi<'_, '_>,
+) -> bool {
+    let Some(asset_path) = value.downcast_mut::<CustomPropAssetPath>() else {
+        return false;
+    };
+
+    asset_path_ui(&mut asset_path.0, id, ui)
+}
+
+fn asset_path_inspector_ui_readonly(
+    value: &dyn Any,
+    ui: &mut egui::Ui,
+    _options: &dyn Any,
+    _id: egui::Id,
+    _env: InspectorUi<'_, '_>,
+) {
+    if let Some(asset_path) = value.downcast_ref::<CustomPropAssetPath>() {
+        ui.label(asset_path.0.as_str());
+    }
+}
+
+fn asset_path_inspector_ui_many(
+    ui: &mut egui::Ui,
+    _options: &dyn Any,
+    _id: egui::Id,
+    _env: InspectorUi<'_, '_>,
+    _values: &mut [&mut dyn Reflect],
+    _projector: &dyn Fn(&mut dyn Reflect) -> &mut dyn Reflect,
+) -> bool {
+    ui.label("Edit asset paths one doodad at a time");
+    false
+}
+
+/// Replaces the plain string inspector of CustomPropAssetPath with asset_path_ui.  The type
+/// has to be registered already, ZoneRuntimePlugin does that.
+pub fn register_asset_path_inspector(type_registry: &mut TypeRegistry) {
+    let Some(registration) = type_registry.get_mut(TypeId::of::<CustomPropAssetPath>()) else {
+        warn!("CustomPropAssetPath is not registered, asset path props get a plain text field");
+        return;
+    };
+
+    registration.insert(InspectorEguiImpl::new(
+        asset_path_inspector_ui,
+        asset_path_inspector_ui_readonly,
+        asset_path_inspector_ui_many,
+    ));
+}
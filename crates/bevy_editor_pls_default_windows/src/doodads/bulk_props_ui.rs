@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_inspector_egui::egui::{self, RichText};
+use bevy_inspector_egui::reflect_inspector;
+
+use crate::zones::zone_file::{CustomProp, CustomPropsComponent, CustomPropsMap};
+
+use super::doodad::DoodadComponent;
+
+/*
+
+Custom props editor for a multi-selection.  Every key found on any selected doodad gets a row
+with the number of doodads that have it and whether their values differ.  The value in the
+row is staged, nothing changes until it is set on all of them, added where the key is missing,
+or the key is removed from all of them.  Each of these is one undo step.
+
+*/
+
+enum BulkPropEdit {
+    //overwrite the key on every doodad
+    SetAll(String, CustomProp),
+    //only doodads without the key get it
+    AddMissing(String, CustomProp),
+    Remove(String),
+}
+
+struct BulkPropRow {
+    key: String,
+    count: usize,
+    values_differ: bool,
+    first_value: CustomProp,
+}
+
+fn bulk_prop_rows(selected_props: &[Option<CustomPropsMap>]) -> Vec<BulkPropRow> {
+    let mut rows: HashMap<String, BulkPropRow> = HashMap::new();
+
+    for props in selected_props.iter().flatten() {
+        for (key, value) in props.iter() {
+            let row = rows.entry(key.clone()).or_insert_with(|| BulkPropRow {
+                key: key.clone(),
+                count: 0,
+                values_differ: false,
+                first_value: value.clone(),
+            });
+
+            row.count += 1;
+            row.values_differ |= row.first_value != *value;
+        }
+    }
+
+    let mut rows: Vec<BulkPropRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| a.key.cmp(&b.key));
+    rows
+}
+
+fn apply_bulk_prop_edit(world: &mut World, entities: &[Entity], edit: &BulkPropEdit) {
+    for entity in entities.iter() {
+        if world.get::<CustomPropsComponent>(*entity).is_none() {
+            if matches!(edit, BulkPropEdit::Remove(_)) {
+                continue;
+            }
+            world
+                .entity_mut(*entity)
+                .insert(CustomPropsComponent::default());
+        }
+
+        let Some(mut custom_props) = world.get_mut::<CustomPropsComponent>(*entity) else {
+            continue;
+        };
+
+        match edit {
+            BulkPropEdit::SetAll(key, value) => {
+                custom_props.set_custom_props(&HashMap::from([(key.clone(), value.clone())]));
+            }
+            BulkPropEdit::AddMissing(key, value) => {
+                custom_props
+                    .set_custom_props_if_empty(&HashMap::from([(key.clone(), value.clone())]));
+            }
+            BulkPropEdit::Remove(key) => {
+                //only touch doodads that have the key, so the others are not marked as changed
+                if custom_props.props.contains_key(key) {
+                    custom_props.props.remove(key);
+                }
+            }
+        }
+    }
+}
+
+//the value being edited in a row, kept by egui between frames
+fn staged_value_ui(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    initial_value: &CustomProp,
+    type_registry: &bevy::reflect::TypeRegistry,
+) -> CustomProp {
+    let mut value: CustomProp = ui
+        .data(|data| data.get_temp(id))
+        .unwrap_or_else(|| initial_value.clone());
+
+    ui.push_id(id, |ui| {
+        reflect_inspector::ui_for_value(&mut value, ui, type_registry);
+    });
+
+    ui.data_mut(|data| data.insert_temp(id, value.clone()));
+    value
+}
+
+/// Shared custom props editor, shown when more than one entity is selected.
+pub(crate) fn bulk_custom_props_ui(world: &mut World, entities: &[Entity], ui: &mut egui::Ui) {
+    //doodads, and anything else that already has custom props
+    let entities: Vec<Entity> = entities
+        .iter()
+        .copied()
+        .filter(|entity| {
+            world.get::<DoodadComponent>(*entity).is_some()
+                || world.get::<CustomPropsComponent>(*entity).is_some()
+        })
+        .collect();
+
+    if entities.len() < 2 {
+        return;
+    }
+
+    let selected_props: Vec<Option<CustomPropsMap>> = entities
+        .iter()
+        .map(|entity| {
+            world
+                .get::<CustomPropsComponent>(*entity)
+                .map(|custom_props| custom_props.props.clone())
+        })
+        .collect();
+    let rows = bulk_prop_rows(&selected_props);
+
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let type_registry = type_registry.read();
+
+    let mut edits: Vec<BulkPropEdit> = Vec::new();
+
+    //staged values belong to this exact selection
+    let selection_id = egui::Id::new(("bulk_custom_props", &entities));
+
+    egui::CollapsingHeader::new(format!("Custom Props ({} doodads)", entities.len()))
+        .id_source("bulk_custom_props")
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid::new("bulk_custom_props_grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for row in rows.iter() {
+                        ui.vertical(|ui| {
+                            ui.label(&row.key);
+
+                            if row.count < entities.len() {
+                                ui.label(
+                                    RichText::new(format!("on {} of {}", row.count, entities.len()))
+                                        .color(egui::Color32::YELLOW),
+                                );
+                            }
+                            if row.values_differ {
+                                ui.label(RichText::new("values differ").color(egui::Color32::YELLOW));
+                            }
+                        });
+
+                        let value = staged_value_ui(
+                            ui,
+                            selection_id.with(("value", &row.key)),
+                            &row.first_value,
+                            &type_registry,
+                        );
+
+                        ui.vertical(|ui| {
+                            if ui.button("Set All").clicked() {
+                                edits.push(BulkPropEdit::SetAll(row.key.clone(), value.clone()));
+                            }
+                            if row.count < entities.len() && ui.button("Add Missing").clicked() {
+                                edits.push(BulkPropEdit::AddMissing(row.key.clone(), value.clone()));
+                            }
+                            if ui.button("Remove").clicked() {
+                                edits.push(BulkPropEdit::Remove(row.key.clone()));
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            ui.label("New key:");
+
+            let new_key_id = selection_id.with("new_key");
+            let mut new_key: String = ui.data(|data| data.get_temp(new_key_id)).unwrap_or_default();
+
+            ui.text_edit_singleline(&mut new_key);
+            let value = staged_value_ui(
+                ui,
+                selection_id.with("new_value"),
+                &CustomProp::EmptyProp,
+                &type_registry,
+            );
+
+            let key = new_key.trim().to_string();
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!key.is_empty(), |ui| {
+                    if ui.button("Set All").clicked() {
+                        edits.push(BulkPropEdit::SetAll(key.clone(), value.clone()));
+                    }
+                    if ui.button("Add Missing").clicked() {
+                        edits.push(BulkPropEdit::AddMissing(key.clone(), value.clone()));
+                    }
+                });
+            });
+
+            ui.data_mut(|data| data.insert_temp(new_key_id, new_key));
+        });
+
+    for edit in edits.iter() {
+        apply_bulk_prop_edit(world, &entities, edit);
+    }
+}
@@ -1,6 +1,5 @@
 use bevy::{prelude::*, utils::HashMap};
 
-use super::{  doodad_manifest::{DoodadDefinition, RenderableType}};
 
 use anyhow::{Context, Result};
 
@@ -40,15 +39,5 @@ pub struct LoadedGltfAssets {
 
 
 
-#[derive(Component, Debug)]
-pub struct DoodadComponent {
-    pub definition: DoodadDefinition,
-}
-
-impl DoodadComponent {
-    pub fn from_definition(definition: &DoodadDefinition) -> Self {
-        Self {
-            definition: definition.clone(),
-        }
-    }
-}
+//shared with games, the runtime crate spawns it for zones loaded as assets
+pub use bevy_zone_runtime::doodad_manifest::DoodadComponent;
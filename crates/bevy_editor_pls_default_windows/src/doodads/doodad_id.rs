@@ -0,0 +1,2 @@
+//doodad ids are saved in zone files, so they live in the runtime crate
+pub use bevy_zone_runtime::doodad_id::{update_doodad_id_index, DoodadId, DoodadIdIndex};
@@ -0,0 +1,650 @@
+use std::error::Error;
+
+use bevy::prelude::*;
+use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
+use bevy_editor_pls_core::EditorEvent;
+use bevy_inspector_egui::egui::{self, RichText};
+
+use crate::cameras::focus_editor_camera;
+use crate::zones::zone_file::{CustomProp, CustomPropsComponent};
+use crate::zones::ZoneComponent;
+
+use super::doodad::DoodadComponent;
+
+/*
+
+Finds loaded doodads by doodad name, manifest tag, zone and custom props.
+
+The name pattern is a substring, or a glob with * and ? when it has either, ignoring case.
+Prop predicates go one per line and all of them have to match:
+
+    loot_table == "boss"
+    level > 5
+    is_locked != true
+    patrol_route          (has the prop)
+    !patrol_route         (does not have the prop)
+
+Numbers compare with == != < <= > >=, strings and booleans only with == and !=.  Quotes around
+strings are optional when the string has no spaces.
+
+*/
+
+type DoodadQueryResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CompareOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl CompareOp {
+    const ALL: [(&'static str, CompareOp); 6] = [
+        ("==", CompareOp::Equal),
+        ("!=", CompareOp::NotEqual),
+        ("<=", CompareOp::LessOrEqual),
+        (">=", CompareOp::GreaterOrEqual),
+        ("<", CompareOp::Less),
+        (">", CompareOp::Greater),
+    ];
+
+    fn compare<T: PartialOrd>(&self, a: T, b: T) -> bool {
+        match self {
+            Self::Equal => a == b,
+            Self::NotEqual => a != b,
+            Self::Less => a < b,
+            Self::LessOrEqual => a <= b,
+            Self::Greater => a > b,
+            Self::GreaterOrEqual => a >= b,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PredicateValue {
+    Number(f64),
+    Boolean(bool),
+    Text(String),
+}
+
+impl PredicateValue {
+    fn parse(value: &str) -> Self {
+        let value = value.trim();
+
+        if let Some(quoted) = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+        {
+            return Self::Text(quoted.to_string());
+        }
+
+        match value {
+            "true" => Self::Boolean(true),
+            "false" => Self::Boolean(false),
+            _ => match value.parse::<f64>() {
+                Ok(number) => Self::Number(number),
+                Err(_) => Self::Text(value.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PropPredicate {
+    Has(String),
+    Missing(String),
+    Compare {
+        key: String,
+        op: CompareOp,
+        value: PredicateValue,
+    },
+}
+
+impl PropPredicate {
+    fn parse(line: &str) -> DoodadQueryResult<Self> {
+        let line = line.trim();
+
+        //the first operator in the line, so quoted values can hold operators.  `>=` rather
+        //than `>` when both start at the same place
+        let operator = CompareOp::ALL
+            .into_iter()
+            .filter_map(|(symbol, op)| line.find(symbol).map(|index| (index, symbol, op)))
+            .min_by_key(|(index, symbol, _)| (*index, std::cmp::Reverse(symbol.len())));
+
+        if let Some((index, symbol, op)) = operator {
+            let (key, value) = (&line[..index], &line[index + symbol.len()..]);
+
+            let key = key.trim();
+            if key.is_empty() || value.trim().is_empty() {
+                return Err(format!("{:?} needs a prop key and a value", line).into());
+            }
+
+            let value = PredicateValue::parse(value);
+            if op != CompareOp::Equal
+                && op != CompareOp::NotEqual
+                && !matches!(value, PredicateValue::Number(_))
+            {
+                return Err(format!("{:?} only numbers can be compared with {}", line, symbol).into());
+            }
+
+            return Ok(Self::Compare {
+                key: key.to_string(),
+                op,
+                value,
+            });
+        }
+
+        match line.strip_prefix('!') {
+            Some(key) if !key.trim().is_empty() => Ok(Self::Missing(key.trim().to_string())),
+            None if !line.contains(char::is_whitespace) => Ok(Self::Has(line.to_string())),
+            _ => Err(format!("{:?} is not a prop predicate", line).into()),
+        }
+    }
+
+    fn matches(&self, custom_props: Option<&CustomPropsComponent>) -> bool {
+        let get = |key: &str| custom_props.and_then(|c| c.props.get(key));
+
+        match self {
+            Self::Has(key) => get(key).is_some(),
+            Self::Missing(key) => get(key).is_none(),
+            Self::Compare { key, op, value } => {
+                let Some(prop) = get(key) else {
+                    return false;
+                };
+
+                match (prop, value) {
+                    (CustomProp::Integer(a), PredicateValue::Number(b)) => op.compare(*a as f64, *b),
+                    //compared as written in the zone file, 0.1 as f32 is not 0.1 as f64
+                    (CustomProp::Float(a), PredicateValue::Number(b)) => op.compare(*a, *b as f32),
+                    (CustomProp::Boolean(a), PredicateValue::Boolean(b)) => op.compare(a, b),
+                    (CustomProp::String(a), PredicateValue::Text(b)) => op.compare(a, b),
+                    (CustomProp::AssetPath(a), PredicateValue::Text(b)) => op.compare(&a.0, b),
+                    //a string prop holding digits, like an id
+                    (CustomProp::String(a), PredicateValue::Number(b)) => {
+                        a.parse::<f64>().map_or(*op == CompareOp::NotEqual, |a| op.compare(a, *b))
+                    }
+                    //values of another type never equal
+                    _ => *op == CompareOp::NotEqual,
+                }
+            }
+        }
+    }
+}
+
+//backtracks to the last star only, so long names cannot blow up the stack or the running time
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    //pattern index after the last star, and the text index that star matched up to
+    let mut last_star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                last_star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match last_star {
+                //let the star take one more character
+                Some((star_p, star_t)) => {
+                    last_star = Some((star_p, star_t + 1));
+                    p = star_p;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    let name = name.to_lowercase();
+
+    match pattern.contains(['*', '?']) {
+        true => glob_matches(
+            &pattern.chars().collect::<Vec<char>>(),
+            &name.chars().collect::<Vec<char>>(),
+        ),
+        false => name.contains(&pattern),
+    }
+}
+
+struct DoodadQuery {
+    name_pattern: String,
+    tag: Option<String>,
+    zone: Option<Entity>,
+    predicates: Vec<PropPredicate>,
+}
+
+impl DoodadQuery {
+    fn from_state(state: &DoodadQueryWindowState) -> DoodadQueryResult<Self> {
+        let predicates = state
+            .predicates
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(PropPredicate::parse)
+            .collect::<DoodadQueryResult<Vec<PropPredicate>>>()?;
+
+        Ok(Self {
+            name_pattern: state.name_pattern.clone(),
+            tag: state.tag.clone(),
+            zone: state.zone,
+            predicates,
+        })
+    }
+
+    fn matches(
+        &self,
+        world: &World,
+        entity: Entity,
+        doodad: &DoodadComponent,
+        name: Option<&Name>,
+        custom_props: Option<&CustomPropsComponent>,
+    ) -> bool {
+        //placed doodads are named after their doodad definition
+        let name = name.map_or("", |name| name.as_str());
+        if !self.name_pattern.trim().is_empty() && !name_matches(&self.name_pattern, name) {
+            return false;
+        }
+
+        if let Some(tag) = &self.tag {
+            let tags = doodad.definition.tags.as_deref().unwrap_or_default();
+            if !tags.contains(tag) {
+                return false;
+            }
+        }
+
+        if let Some(zone) = self.zone {
+            if doodad_zone(world, entity) != Some(zone) {
+                return false;
+            }
+        }
+
+        self.predicates
+            .iter()
+            .all(|predicate| predicate.matches(custom_props))
+    }
+}
+
+//the closest zone above a doodad, zone instances count as part of the zone they are placed in
+fn doodad_zone(world: &World, entity: Entity) -> Option<Entity> {
+    let mut current = world.get::<Parent>(entity).map(|parent| parent.get());
+
+    while let Some(ancestor) = current {
+        if world.get::<ZoneComponent>(ancestor).is_some() {
+            return Some(ancestor);
+        }
+        current = world.get::<Parent>(ancestor).map(|parent| parent.get());
+    }
+
+    None
+}
+
+fn run_doodad_query(world: &mut World, query: &DoodadQuery) -> Vec<Entity> {
+    let mut doodad_query = world.query::<(
+        Entity,
+        &DoodadComponent,
+        Option<&Name>,
+        Option<&CustomPropsComponent>,
+    )>();
+
+    let mut results: Vec<(String, Entity)> = doodad_query
+        .iter(world)
+        .filter(|(entity, doodad, name, custom_props)| {
+            query.matches(world, *entity, doodad, *name, *custom_props)
+        })
+        .map(|(entity, _, name, _)| (name.map_or(String::new(), |name| name.to_string()), entity))
+        .collect();
+
+    results.sort();
+    results.into_iter().map(|(_, entity)| entity).collect()
+}
+
+#[derive(Default)]
+pub struct DoodadQueryWindowState {
+    name_pattern: String,
+    tag: Option<String>,
+    zone: Option<Entity>,
+    predicates: String,
+    results: Vec<Entity>,
+    error: Option<String>,
+}
+
+pub struct DoodadQueryWindow;
+
+impl EditorWindow for DoodadQueryWindow {
+    type State = DoodadQueryWindowState;
+    const NAME: &'static str = "Query";
+
+    fn ui(world: &mut World, mut cx: EditorWindowContext, ui: &mut egui::Ui) {
+        let state = cx.state_mut::<DoodadQueryWindow>().unwrap();
+
+        let mut all_tags: Vec<String> = world
+            .query::<&DoodadComponent>()
+            .iter(world)
+            .flat_map(|doodad| doodad.definition.tags.clone().unwrap_or_default())
+            .collect();
+        all_tags.sort();
+        all_tags.dedup();
+
+        let mut zones: Vec<(Entity, String)> = world
+            .query_filtered::<(Entity, &Name), With<ZoneComponent>>()
+            .iter(world)
+            .map(|(entity, name)| (entity, name.to_string()))
+            .collect();
+        zones.sort_by(|a, b| a.1.cmp(&b.1));
+
+        //a zone that was unloaded no longer filters anything
+        if state.zone.is_some_and(|zone| !zones.iter().any(|(entity, _)| *entity == zone)) {
+            state.zone = None;
+        }
+
+        let mut run_query = false;
+
+        egui::Grid::new("doodad_query_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Name");
+                run_query |= egui::TextEdit::singleline(&mut state.name_pattern)
+                    .hint_text("rock*")
+                    .show(ui)
+                    .response
+                    .lost_focus();
+                ui.end_row();
+
+                ui.label("Tag");
+                egui::ComboBox::from_id_source("doodad_query_tag")
+                    .selected_text(state.tag.as_deref().unwrap_or("Any"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut state.tag, None, "Any");
+                        for tag in all_tags.iter() {
+                            ui.selectable_value(&mut state.tag, Some(tag.clone()), tag);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Zone");
+                let selected_zone = zones
+                    .iter()
+                    .find(|(entity, _)| Some(*entity) == state.zone)
+                    .map_or("Any", |(_, name)| name.as_str());
+                egui::ComboBox::from_id_source("doodad_query_zone")
+                    .selected_text(selected_zone)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut state.zone, None, "Any");
+                        for (entity, name) in zones.iter() {
+                            ui.selectable_value(&mut state.zone, Some(*entity), name);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Props");
+                egui::TextEdit::multiline(&mut state.predicates)
+                    .hint_text("level > 5\nloot_table == \"boss\"")
+                    .desired_rows(3)
+                    .show(ui);
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            run_query |= ui.button("Search").clicked();
+            if ui.button("Clear").clicked() {
+                *state = DoodadQueryWindowState::default();
+            }
+        });
+
+        if run_query {
+            match DoodadQuery::from_state(state) {
+                Ok(query) => {
+                    state.results = run_doodad_query(world, &query);
+                    state.error = None;
+                }
+                Err(error) => {
+                    state.results.clear();
+                    state.error = Some(error.to_string());
+                }
+            }
+        }
+
+        if let Some(error) = &state.error {
+            ui.label(RichText::new(error).color(egui::Color32::RED));
+        }
+
+        //despawned doodads drop out of the results
+        state
+            .results
+            .retain(|entity| world.get::<DoodadComponent>(*entity).is_some());
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{} doodads", state.results.len()));
+
+            ui.add_enabled_ui(!state.results.is_empty(), |ui| {
+                if ui.button("Select All").clicked() {
+                    world.send_event(EditorEvent::SetSelectedEntities(Some(state.results.clone())));
+                }
+                if ui.button("Focus All").clicked() {
+                    focus_editor_camera(world, &state.results);
+                }
+            });
+        });
+
+        let mut select: Option<Entity> = None;
+        let mut focus: Option<Entity> = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for entity in state.results.iter() {
+                    let label = world
+                        .get::<Name>(*entity)
+                        .map_or_else(|| format!("{:?}", entity), |name| format!("{} ({:?})", name, entity));
+
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(false, label).clicked() {
+                            select = Some(*entity);
+                        }
+                        if ui.small_button("Focus").clicked() {
+                            select = Some(*entity);
+                            focus = Some(*entity);
+                        }
+                    });
+                }
+            });
+
+        if let Some(entity) = select {
+            world.send_event(EditorEvent::SetSelectedEntities(Some(vec![entity])));
+        }
+        if let Some(entity) = focus {
+            focus_editor_camera(world, &[entity]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(key: &str, op: CompareOp, value: PredicateValue) -> PropPredicate {
+        PropPredicate::Compare {
+            key: key.to_string(),
+            op,
+            value,
+        }
+    }
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        glob_matches(
+            &pattern.chars().collect::<Vec<char>>(),
+            &text.chars().collect::<Vec<char>>(),
+        )
+    }
+
+    #[test]
+    fn parses_comparisons() {
+        assert_eq!(
+            PropPredicate::parse("loot_table == \"boss\"").unwrap(),
+            compare("loot_table", CompareOp::Equal, PredicateValue::Text("boss".into()))
+        );
+        assert_eq!(
+            PropPredicate::parse("level>5").unwrap(),
+            compare("level", CompareOp::Greater, PredicateValue::Number(5.0))
+        );
+        assert_eq!(
+            PropPredicate::parse("  level >= 5  ").unwrap(),
+            compare("level", CompareOp::GreaterOrEqual, PredicateValue::Number(5.0))
+        );
+        assert_eq!(
+            PropPredicate::parse("level <= -1.5").unwrap(),
+            compare("level", CompareOp::LessOrEqual, PredicateValue::Number(-1.5))
+        );
+        assert_eq!(
+            PropPredicate::parse("is_locked != true").unwrap(),
+            compare("is_locked", CompareOp::NotEqual, PredicateValue::Boolean(true))
+        );
+    }
+
+    #[test]
+    fn splits_at_the_first_operator() {
+        assert_eq!(
+            PropPredicate::parse("note != \"a==b\"").unwrap(),
+            compare("note", CompareOp::NotEqual, PredicateValue::Text("a==b".into()))
+        );
+        assert_eq!(
+            PropPredicate::parse("note == \"x<=y\"").unwrap(),
+            compare("note", CompareOp::Equal, PredicateValue::Text("x<=y".into()))
+        );
+    }
+
+    #[test]
+    fn parses_has_and_missing() {
+        assert_eq!(
+            PropPredicate::parse("patrol_route").unwrap(),
+            PropPredicate::Has("patrol_route".into())
+        );
+        assert_eq!(
+            PropPredicate::parse("! patrol_route").unwrap(),
+            PropPredicate::Missing("patrol_route".into())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_predicates() {
+        assert!(PropPredicate::parse("== 5").is_err());
+        assert!(PropPredicate::parse("level >").is_err());
+        assert!(PropPredicate::parse("name > \"rock\"").is_err());
+        assert!(PropPredicate::parse("two words").is_err());
+        assert!(PropPredicate::parse("!").is_err());
+    }
+
+    fn props(entries: &[(&str, CustomProp)]) -> CustomPropsComponent {
+        CustomPropsComponent {
+            props: entries
+                .iter()
+                .map(|(key, prop)| (key.to_string(), prop.clone()))
+                .collect(),
+        }
+    }
+
+    fn matches(line: &str, custom_props: &CustomPropsComponent) -> bool {
+        PropPredicate::parse(line).unwrap().matches(Some(custom_props))
+    }
+
+    #[test]
+    fn matches_floats_as_f32() {
+        let custom_props = props(&[("speed", CustomProp::Float(0.1))]);
+
+        assert!(matches("speed == 0.1", &custom_props));
+        assert!(!matches("speed != 0.1", &custom_props));
+        assert!(matches("speed <= 0.1", &custom_props));
+        assert!(!matches("speed > 0.1", &custom_props));
+    }
+
+    #[test]
+    fn matches_integer_ranges() {
+        let custom_props = props(&[("level", CustomProp::Integer(5))]);
+
+        assert!(matches("level == 5", &custom_props));
+        assert!(matches("level >= 5", &custom_props));
+        assert!(matches("level > 4.5", &custom_props));
+        assert!(matches("level < 6", &custom_props));
+        assert!(!matches("level > 5", &custom_props));
+        assert!(!matches("level <= 4", &custom_props));
+    }
+
+    #[test]
+    fn type_mismatches_are_never_equal() {
+        let custom_props = props(&[
+            ("is_locked", CustomProp::Boolean(true)),
+            ("loot_table", CustomProp::String("boss".into())),
+            ("door_id", CustomProp::String("12".into())),
+        ]);
+
+        assert!(!matches("is_locked == 1", &custom_props));
+        assert!(matches("is_locked != 1", &custom_props));
+        assert!(!matches("loot_table == true", &custom_props));
+        assert!(!matches("loot_table > 3", &custom_props));
+        assert!(matches("loot_table == boss", &custom_props));
+        //strings holding digits compare as numbers
+        assert!(matches("door_id == 12", &custom_props));
+        assert!(matches("door_id < 13", &custom_props));
+    }
+
+    #[test]
+    fn missing_keys() {
+        let custom_props = props(&[("patrol_route", CustomProp::EmptyProp)]);
+
+        assert!(matches("patrol_route", &custom_props));
+        assert!(!matches("!patrol_route", &custom_props));
+        assert!(!matches("level == 5", &custom_props));
+        assert!(!matches("level != 5", &custom_props));
+        assert!(matches("!level", &custom_props));
+
+        //doodads without custom props have no keys at all
+        assert!(!PropPredicate::parse("patrol_route").unwrap().matches(None));
+        assert!(PropPredicate::parse("!patrol_route").unwrap().matches(None));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob("rock*", "rock_large"));
+        assert!(glob("*large", "rock_large"));
+        assert!(glob("r?ck", "rock"));
+        assert!(glob("*", ""));
+        assert!(glob("a*b*c", "axxbyyc"));
+        assert!(!glob("r?ck", "rck"));
+        assert!(!glob("rock", "rock_large"));
+        assert!(!glob("*small", "rock_large"));
+        assert!(glob("*a*b", "aaab_ab"));
+        assert!(!glob("a*?", "a"));
+    }
+
+    #[test]
+    fn globs_long_names_quickly() {
+        let name: String = "a".repeat(10_000);
+        let pattern: String = "*a".repeat(50) + "b";
+
+        assert!(!glob(&pattern, &name));
+        assert!(glob(&format!("{}*", pattern.trim_end_matches('b')), &name));
+    }
+
+    #[test]
+    fn names() {
+        assert!(name_matches("rock", "Big_Rock_01"));
+        assert!(name_matches("ROCK*", "rock_01"));
+        assert!(name_matches("  tree  ", "pine_tree"));
+        assert!(name_matches("*_0?", "rock_01"));
+        assert!(!name_matches("rock*", "big_rock"));
+        assert!(!name_matches("bush", "rock"));
+    }
+}
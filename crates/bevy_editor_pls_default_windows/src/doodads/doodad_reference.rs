@@ -0,0 +1,412 @@
+use bevy::prelude::*;
+use bevy_editor_pls_core::Editor;
+use bevy_inspector_egui::bevy_egui::EguiContexts;
+use bevy_inspector_egui::egui::{self, RichText};
+use bevy_mod_raycast::{cursor::CursorRay, immediate::Raycast};
+
+use crate::hierarchy::HierarchyWindow;
+use crate::zones::zone_file::{custom_prop_doodad_references, CustomProp, CustomPropsComponent};
+
+use super::doodad::DoodadComponent;
+use super::doodad_id::{DoodadId, DoodadIdIndex};
+
+/*
+
+DoodadReference custom props point at another placed doodad by its DoodadId, so the link
+survives saving and loading the zone.
+
+In the inspector, Pick waits for a click on a doodad in the viewport and stores its id under
+the prop key.  Links are drawn as arrows from each doodad to the doodads it references.  When a
+referenced doodad is deleted, the references to it are listed in the Doodads window until they
+are fixed or the target comes back, for example with undo.
+
+*/
+
+const LINK_COLOR: Color = Color::srgb(0.2, 0.8, 1.0);
+const BROKEN_LINK_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+
+pub struct BrokenDoodadReference {
+    pub source: Entity,
+    pub source_name: String,
+    pub key: String,
+    pub target: DoodadId,
+}
+
+#[derive(Resource)]
+pub struct DoodadReferenceResource {
+    //doodad and prop key waiting for a target to be clicked in the viewport
+    pub picking: Option<(Entity, String)>,
+    //the left click of this frame went to picking, so it does not also select a doodad
+    pub consumed_click: bool,
+    //entity to select in the hierarchy, set from windows that cannot reach the editor state
+    pub select_request: Option<Entity>,
+    pub broken_references: Vec<BrokenDoodadReference>,
+    pub show_links: bool,
+}
+
+impl Default for DoodadReferenceResource {
+    fn default() -> Self {
+        Self {
+            picking: None,
+            consumed_click: false,
+            select_request: None,
+            broken_references: Vec::new(),
+            show_links: true,
+        }
+    }
+}
+
+//the doodad that was clicked, or the doodad a clicked mesh belongs to
+fn doodad_at_hit(
+    hit_entity: Entity,
+    doodad_query: &Query<&DoodadComponent>,
+    parent_query: &Query<&Parent>,
+) -> Option<Entity> {
+    std::iter::once(hit_entity)
+        .chain(AncestorIter::new(parent_query, hit_entity))
+        .find(|entity| doodad_query.contains(*entity))
+}
+
+pub fn pick_doodad_reference_target(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    cursor_ray: Res<CursorRay>,
+    mut raycast: Raycast,
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+    mut doodad_reference_resource: ResMut<DoodadReferenceResource>,
+
+    doodad_query: Query<&DoodadComponent>,
+    parent_query: Query<&Parent>,
+    doodad_id_query: Query<&DoodadId>,
+    mut custom_props_query: Query<&mut CustomPropsComponent>,
+) {
+    if let Some(entity) = doodad_reference_resource.select_request.take() {
+        if let Some(state) = editor.window_state_mut::<HierarchyWindow>() {
+            state.selected.select_replace(entity);
+        }
+    }
+
+    doodad_reference_resource.consumed_click = false;
+
+    let Some((source, key)) = doodad_reference_resource.picking.clone() else {
+        return;
+    };
+
+    if key_input.just_pressed(KeyCode::Escape) || mouse_input.just_pressed(MouseButton::Right) {
+        doodad_reference_resource.picking = None;
+        return;
+    }
+
+    if !mouse_input.just_pressed(MouseButton::Left) || contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+
+    doodad_reference_resource.consumed_click = true;
+
+    let Some(cursor_ray) = **cursor_ray else {
+        return;
+    };
+
+    let Some(target) = raycast
+        .cast_ray(cursor_ray, &default())
+        .first()
+        .and_then(|(hit_entity, _)| doodad_at_hit(*hit_entity, &doodad_query, &parent_query))
+    else {
+        return;
+    };
+
+    if target == source {
+        println!("a doodad cannot reference itself");
+        return;
+    }
+
+    let Ok(target_id) = doodad_id_query.get(target) else {
+        println!("doodad {:?} has no id to reference", target);
+        return;
+    };
+
+    let prop = CustomProp::DoodadReference(*target_id);
+    match custom_props_query.get_mut(source) {
+        Ok(mut custom_props) => {
+            custom_props.props.insert(key.clone(), prop);
+        }
+        Err(_) => {
+            let mut custom_props = CustomPropsComponent::default();
+            custom_props.props.insert(key.clone(), prop);
+            commands.entity(source).insert(custom_props);
+        }
+    }
+
+    println!("{:?} {} now references {}", source, key, target_id);
+
+    doodad_reference_resource.picking = None;
+}
+
+/// Runs before the id index forgets removed doodads, so their ids are still known here.
+pub fn track_broken_doodad_references(
+    mut doodad_reference_resource: ResMut<DoodadReferenceResource>,
+    doodad_id_index: Res<DoodadIdIndex>,
+    mut removed_ids: RemovedComponents<DoodadId>,
+    custom_props_query: Query<(Entity, &CustomPropsComponent, Option<&Name>)>,
+) {
+    //ids with no doodad left once this frame's removals are applied
+    let mut removed_entities: Vec<Entity> = removed_ids.read().collect();
+    removed_entities.sort();
+
+    let mut deleted_ids: Vec<DoodadId> = removed_entities
+        .iter()
+        .filter_map(|entity| doodad_id_index.get_id(*entity))
+        .filter(|id| {
+            doodad_id_index
+                .get_entities(*id)
+                .iter()
+                .all(|entity| removed_entities.binary_search(entity).is_ok())
+        })
+        .collect();
+    deleted_ids.dedup();
+
+    if !deleted_ids.is_empty() {
+        for (source, custom_props, name) in custom_props_query.iter() {
+            for (key, target) in custom_prop_doodad_references(&custom_props.props) {
+                if !deleted_ids.contains(&target) {
+                    continue;
+                }
+
+                let source_name = name.map_or_else(|| format!("{:?}", source), |n| n.to_string());
+                eprintln!("{} {} references deleted doodad {}", source_name, key, target);
+
+                doodad_reference_resource.broken_references.push(BrokenDoodadReference {
+                    source,
+                    source_name,
+                    key,
+                    target,
+                });
+            }
+        }
+    }
+
+    if doodad_reference_resource.broken_references.is_empty() {
+        return;
+    }
+
+    //fixed, or the source is gone too, or the target came back
+    let still_broken = |broken: &BrokenDoodadReference| {
+        let Ok((_, custom_props, _)) = custom_props_query.get(broken.source) else {
+            return false;
+        };
+
+        let target_gone = deleted_ids.contains(&broken.target)
+            || doodad_id_index.get_entities(broken.target).is_empty();
+
+        target_gone
+            && custom_prop_doodad_references(&custom_props.props)
+                .iter()
+                .any(|(key, target)| *key == broken.key && *target == broken.target)
+    };
+
+    doodad_reference_resource
+        .broken_references
+        .retain(|broken| still_broken(broken));
+}
+
+pub fn draw_doodad_reference_links(
+    mut gizmos: Gizmos,
+    doodad_reference_resource: Res<DoodadReferenceResource>,
+    doodad_id_index: Res<DoodadIdIndex>,
+    custom_props_query: Query<(&CustomPropsComponent, &GlobalTransform)>,
+    transform_query: Query<&GlobalTransform>,
+) {
+    if !doodad_reference_resource.show_links {
+        return;
+    }
+
+    for (custom_props, source_transform) in custom_props_query.iter() {
+        let start = source_transform.translation();
+
+        for (_, target) in custom_prop_doodad_references(&custom_props.props) {
+            let targets = doodad_id_index.get_entities(target);
+
+            if targets.is_empty() {
+                //a short stub marks the source of a broken reference
+                gizmos.line(start, start + Vec3::Y * 2.0, BROKEN_LINK_COLOR);
+                continue;
+            }
+
+            for target_entity in targets {
+                if let Ok(target_transform) = transform_query.get(*target_entity) {
+                    gizmos.arrow(start, target_transform.translation(), LINK_COLOR);
+                }
+            }
+        }
+    }
+}
+
+fn doodad_label(world: &World, entity: Entity) -> String {
+    world
+        .get::<Name>(entity)
+        .map_or_else(|| format!("{:?}", entity), |name| format!("{} ({:?})", name, entity))
+}
+
+/// The references of the selected doodad, with Pick to set them from the viewport, and the
+/// doodads that reference it.
+pub(crate) fn doodad_references_ui(world: &mut World, entity: Entity, ui: &mut egui::Ui) {
+    let Some(doodad_id) = world.get::<DoodadId>(entity).copied() else {
+        return;
+    };
+
+    let references = world
+        .get::<CustomPropsComponent>(entity)
+        .map(|custom_props| custom_prop_doodad_references(&custom_props.props))
+        .unwrap_or_default();
+
+    let mut referenced_by: Vec<(Entity, String)> = Vec::new();
+    let mut custom_props_query = world.query::<(Entity, &CustomPropsComponent)>();
+    for (source, custom_props) in custom_props_query.iter(world) {
+        for (key, target) in custom_prop_doodad_references(&custom_props.props) {
+            if target == doodad_id {
+                referenced_by.push((source, key));
+            }
+        }
+    }
+
+    let picking = world.resource::<DoodadReferenceResource>().picking.clone();
+
+    let mut start_picking: Option<String> = None;
+    let mut select: Option<Entity> = None;
+
+    egui::CollapsingHeader::new("Doodad References")
+        .id_source(("doodad_references", entity))
+        .default_open(!references.is_empty() || !referenced_by.is_empty())
+        .show(ui, |ui| {
+            if let Some((picking_entity, key)) = &picking {
+                if *picking_entity == entity {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(format!(
+                                "Click a doodad in the viewport for {} (Esc to cancel)",
+                                key
+                            ))
+                            .color(egui::Color32::YELLOW),
+                        );
+                        if ui.button("Cancel").clicked() {
+                            world.resource_mut::<DoodadReferenceResource>().picking = None;
+                        }
+                    });
+                }
+            }
+
+            egui::Grid::new(("doodad_references_grid", entity))
+                .num_columns(3)
+                .show(ui, |ui| {
+                    for (key, target) in references.iter() {
+                        ui.label(key);
+
+                        match world.resource::<DoodadIdIndex>().get_entity(*target) {
+                            Some(target_entity) => {
+                                if ui.link(doodad_label(world, target_entity)).clicked() {
+                                    select = Some(target_entity);
+                                }
+                            }
+                            None => {
+                                ui.label(
+                                    RichText::new(format!("missing doodad {}", target))
+                                        .color(egui::Color32::RED),
+                                );
+                            }
+                        }
+
+                        //only top level props can be picked, nested ones are edited by id
+                        if !key.contains(['[', '.']) && ui.small_button("Pick").clicked() {
+                            start_picking = Some(key.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                let new_key_id = ui.id().with(("new_reference_key", entity));
+                let mut new_key: String =
+                    ui.data(|data| data.get_temp(new_key_id)).unwrap_or_default();
+
+                ui.text_edit_singleline(&mut new_key);
+                if ui
+                    .add_enabled(!new_key.trim().is_empty(), egui::Button::new("Pick New"))
+                    .clicked()
+                {
+                    start_picking = Some(new_key.trim().to_string());
+                    new_key.clear();
+                }
+
+                ui.data_mut(|data| data.insert_temp(new_key_id, new_key));
+            });
+
+            if !referenced_by.is_empty() {
+                ui.label("Referenced by:");
+                for (source, key) in referenced_by.iter() {
+                    if ui
+                        .link(format!("{} {}", doodad_label(world, *source), key))
+                        .clicked()
+                    {
+                        select = Some(*source);
+                    }
+                }
+            }
+        });
+
+    let mut doodad_reference_resource = world.resource_mut::<DoodadReferenceResource>();
+    if let Some(key) = start_picking {
+        doodad_reference_resource.picking = Some((entity, key));
+    }
+    if select.is_some() {
+        doodad_reference_resource.select_request = select;
+    }
+}
+
+/// Link display toggle and the references left dangling by deleted doodads.
+pub fn broken_doodad_references_ui(world: &mut World, ui: &mut egui::Ui) {
+    let mut show_links = world.resource::<DoodadReferenceResource>().show_links;
+    if ui.checkbox(&mut show_links, "Show doodad reference links").changed() {
+        world.resource_mut::<DoodadReferenceResource>().show_links = show_links;
+    }
+
+    let broken_references: Vec<(Entity, String)> = world
+        .resource::<DoodadReferenceResource>()
+        .broken_references
+        .iter()
+        .map(|broken| {
+            (
+                broken.source,
+                format!("{} {} -> deleted doodad {}", broken.source_name, broken.key, broken.target),
+            )
+        })
+        .collect();
+
+    if broken_references.is_empty() {
+        return;
+    }
+
+    ui.label(
+        RichText::new(format!("{} broken doodad references:", broken_references.len()))
+            .color(egui::Color32::RED),
+    );
+
+    let mut select: Option<Entity> = None;
+    for (source, label) in broken_references.iter() {
+        if ui.link(label).clicked() {
+            select = Some(*source);
+        }
+    }
+
+    if ui.button("Dismiss").clicked() {
+        world
+            .resource_mut::<DoodadReferenceResource>()
+            .broken_references
+            .clear();
+    }
+
+    if select.is_some() {
+        world.resource_mut::<DoodadReferenceResource>().select_request = select;
+    }
+}
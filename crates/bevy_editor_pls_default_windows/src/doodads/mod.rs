@@ -9,28 +9,38 @@ use bevy::utils::HashMap;
 
 
 use crate::doodads::doodad_manifest::RenderableType;
+use crate::history::doodad_commands::{DoodadSnapshot, SnapshotEntry, SpawnDoodadsCommand};
+use crate::history::EditorHistory;
 use crate::placement::PlacementWindow;
 use crate::zones::zone_file::{CustomPropsComponent,CustomPropsMap};
-use crate::zones::ZoneResource;
+use crate::zones::{SpawnedFromZoneFile, ZoneResource};
 use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
 use bevy_editor_pls_core::{Editor, EditorEvent};
 use bevy_inspector_egui::bevy_egui::EguiContexts;
 use bevy_inspector_egui::egui::{self, ScrollArea};
 
-use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_zone_runtime::doodad_id::update_doodad_id_index;
+use bevy_zone_runtime::ZoneRuntimePlugin;
 
 use bevy_mod_raycast::cursor::CursorRay;
 
 use bevy_mod_raycast::prelude::Raycast;
 
 use self::doodad::{DoodadComponent,    };
+use self::doodad_id::DoodadId;
 use self::doodad_manifest::{DoodadDefinition, DoodadManifest, DoodadManifestResource, DoodadTagMapResource};
 use self::doodad_placement_preview::DoodadPlacementComponent;
 
  
 
+pub mod asset_path_ui;
+pub mod bulk_props_ui;
 pub mod doodad_manifest;
+pub mod doodad_query;
+pub mod doodad_reference;
+pub mod doodad_id;
 pub mod picking;
+pub mod prop_schema_ui;
 pub mod doodad;
 pub mod doodad_placement_preview;
 
@@ -42,6 +52,7 @@ impl Plugin for DoodadPlugin {
          app
 
              .add_event::< PlaceDoodadEvent>()
+             .init_resource::<doodad_reference::DoodadReferenceResource>()
                .add_event::< DoodadToolEvent>()
 
             //.init_resource::<DoodadTagMapResource>()
@@ -53,7 +64,9 @@ impl Plugin for DoodadPlugin {
            
             .add_systems(Update, reset_place_doodads)
             .add_systems(Update, (handle_place_doodad_events,handle_doodad_tool_events , replace_proto_doodads_with_doodads).chain()  )
-            .add_systems(Update, picking::update_picking_doodads)
+            .add_systems(Update, (doodad_reference::pick_doodad_reference_target, picking::update_picking_doodads).chain())
+            .add_systems(Update, doodad_reference::track_broken_doodad_references.before(update_doodad_id_index))
+            .add_systems(Update, doodad_reference::draw_doodad_reference_links)
            
 
             ;
@@ -92,9 +105,17 @@ pub struct PlaceDoodadEvent {
     pub position: Vec3,
     pub scale: Option<Vec3>,
     pub rotation_euler: Option<Vec3>,
+    //takes precedence over rotation_euler
+    pub rotation: Option<Quat>,
     pub doodad_name: String,
     pub custom_props: Option<CustomPropsMap>,
-    pub zone: Option<Entity> 
+    pub zone: Option<Entity>,
+    //parent under another doodad instead of the zone
+    pub parent: Option<Entity>,
+    //build the doodad on an entity reserved ahead of time, so zone loading can parent children to it
+    pub reserved_entity: Option<Entity>,
+    //id saved in the zone file, none gives the doodad a new id
+    pub id: Option<DoodadId>,
     // pub doodad_definition: DoodadDefinition
 }
 
@@ -112,18 +133,25 @@ impl EditorWindow for DoodadsWindow {
 
     /// Necessary setup (resources, systems) for the window.
     fn app_setup(app: &mut App) {
-        app.add_plugins(RonAssetPlugin::<DoodadManifest>::new(&["doodadmanifest.ron"]))
+        //loads the doodad manifest, and zone files for games, with the same code a game uses
+        app.add_plugins(ZoneRuntimePlugin)
             
-            .insert_resource(DoodadManifestResource::default())
             .insert_resource(DoodadTagMapResource::default())
             .insert_resource(DoodadToolState::default())
           //  .insert_resource(LoadedGltfAssets::default())
 
            ;
+
+        asset_path_ui::register_asset_path_inspector(
+            &mut app.world().resource::<AppTypeRegistry>().write(),
+        );
         
     }
 
     fn ui(world: &mut World, mut cx: EditorWindowContext, ui: &mut egui::Ui) {
+        doodad_reference::broken_doodad_references_ui(world, ui);
+        ui.separator();
+
         let doodad_definition_resource = world.resource::<DoodadManifestResource>();
 
          let doodad_tag_map_resource = world.resource::<DoodadTagMapResource>();
@@ -254,6 +282,8 @@ pub fn handle_place_doodad_events(
 
     zone_resource: Res<ZoneResource>,
 
+    mut history: ResMut<EditorHistory>,
+
    // doodad_manifest_resource: Res<DoodadManifestResource>,
    // doodad_manifest_assets: Res<Assets<DoodadManifest>>,
 ) {
@@ -262,6 +292,9 @@ pub fn handle_place_doodad_events(
 
    // let manifest = manifest_handle.as_ref().map( |handle| doodad_manifest_assets.get(handle) ).flatten();
 
+    //placements from one batch, like a glTF import, are selected and undone together
+    let mut placed_entries: Vec<SnapshotEntry> = Vec::new();
+
     for evt in evt_reader.read() {
         let position = &evt.position;
         let doodad_name = &evt.doodad_name;
@@ -271,7 +304,9 @@ pub fn handle_place_doodad_events(
 
         let mut transform = Transform::from_xyz(position.x, position.y, position.z);
 
-        if let Some(rot) = evt.rotation_euler {
+        if let Some(rot) = evt.rotation {
+            transform = transform.with_rotation(rot)
+        } else if let Some(rot) = evt.rotation_euler {
             transform =
                 transform.with_rotation(Quat::from_euler(EulerRot::YXZ, rot.x, rot.y, rot.z))
         }
@@ -279,20 +314,24 @@ pub fn handle_place_doodad_events(
             transform = transform.with_scale(scale)
         }
 
-        let doodad_spawned = commands
-            .spawn(SpatialBundle {
+        let doodad_id = evt.id.unwrap_or_else(DoodadId::new_random);
+
+        let mut doodad_entity_commands = match evt.reserved_entity.and_then(|ent| commands.get_entity(ent)) {
+            Some(entity_commands) => entity_commands,
+            None => commands.spawn_empty(),
+        };
+
+        let doodad_spawned = doodad_entity_commands
+            .insert(SpatialBundle {
                 transform,
                 ..default()
             })
             .insert(Name::new(doodad_name.clone())  )
             .insert( DoodadProto )
+            .insert( doodad_id )
             .id();
 
 
-        editor_event_writer.send( 
-            EditorEvent::SetSelectedEntities(Some(vec![ doodad_spawned ]))
-         );
-
         doodad_tool_event_writer.send(
             DoodadToolEvent::SetSelectedDoodad(None) 
         );
@@ -324,7 +363,11 @@ pub fn handle_place_doodad_events(
 
 
 
-         if let Some(zone_override) = &evt.zone {
+         if let Some(parent) = &evt.parent {
+            if let Some(mut ent) = commands.get_entity(parent.clone()) {
+                ent.add_child(doodad_spawned);
+            }
+        }else if let Some(zone_override) = &evt.zone {
             if let Some(mut ent) = commands.get_entity(zone_override.clone()) {
                 ent.add_child(doodad_spawned);
             }
@@ -333,6 +376,39 @@ pub fn handle_place_doodad_events(
                 ent.add_child(doodad_spawned);
             }
         }
+
+        //zone loading passes an explicit zone, those spawns are not user edits
+        if evt.zone.is_some() {
+            commands.entity(doodad_spawned).insert(SpawnedFromZoneFile);
+        }
+
+        if evt.zone.is_none() {
+            placed_entries.push(SnapshotEntry {
+                parent: zone_resource.primary_zone,
+                snapshot: DoodadSnapshot {
+                    entity: doodad_spawned,
+                    name: doodad_name.clone(),
+                    transform,
+                    custom_props: evt.custom_props.clone(),
+                    id: Some(doodad_id),
+                    is_zone: false,
+                    zone_metadata: None,
+                    zone_file_path: None,
+                    zone_instance: None,
+                    children: Vec::new(),
+                },
+            });
+        }
+    }
+
+    if !placed_entries.is_empty() {
+        editor_event_writer.send(EditorEvent::SetSelectedEntities(Some(
+            placed_entries.iter().map(|entry| entry.snapshot.entity).collect(),
+        )));
+
+        history.push(SpawnDoodadsCommand {
+            entries: placed_entries,
+        });
     }
 }
 
@@ -527,9 +603,13 @@ pub fn update_place_doodads(
                 position: hit_coordinates,
                 doodad_name: doodad_definition_name,
                 rotation_euler,
+                rotation: None,
                 scale,
                 custom_props,
-                zone: None
+                zone: None,
+                parent: None,
+                reserved_entity: None,
+                id: None,
             });
         }
     }
@@ -4,7 +4,12 @@ use bevy_editor_pls_core::Editor;
 use bevy_inspector_egui::bevy_egui::EguiContexts;
 use bevy_mod_raycast::{immediate::Raycast, cursor::CursorRay};
 
-use super::{doodad::DoodadComponent, DoodadToolState, PlaceDoodadEvent};
+use crate::zones::zone_instance::ZoneInstanceComponent;
+
+use super::{
+    doodad::DoodadComponent, doodad_reference::DoodadReferenceResource, DoodadToolState,
+    PlaceDoodadEvent,
+};
 
 #[derive(Event)]
 pub struct SelectDoodadEvent {
@@ -29,7 +34,15 @@ pub fn update_picking_doodads(
     unpickable_query: Query<&PreventEditorSelection>,
     doodad_comp_query: Query<&DoodadComponent>,
     parent_query: Query<&Parent>,
+    zone_instance_query: Query<(), With<ZoneInstanceComponent>>,
+
+    doodad_reference_resource: Res<DoodadReferenceResource>,
 ) {
+    //the click picks a reference target instead
+    if doodad_reference_resource.picking.is_some() || doodad_reference_resource.consumed_click {
+        return;
+    }
+
     let state = editor.window_state_mut::<HierarchyWindow>().unwrap();
 
     if !mouse_input.just_pressed(MouseButton::Left) {
@@ -73,6 +86,12 @@ pub fn update_picking_doodads(
 
             let focus_entity = top_doodad_comp_parent_entity.unwrap_or(intersection_entity.clone());
 
+            //instanced doodads are edited in their source zone
+            if AncestorIter::new(&parent_query, focus_entity).any(|e| zone_instance_query.contains(e)) {
+                println!("This doodad is part of a zone instance, edit its source zone instead");
+                return;
+            }
+
             state.selected.select_replace(focus_entity.clone());
 
             event_writer.send(SelectDoodadEvent {
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy_inspector_egui::egui::{self, DragValue, RichText};
+use bevy_inspector_egui::reflect_inspector;
+use bevy_zone_runtime::prop_schema::{CustomPropSchema, CustomPropType, CustomPropViolation};
+
+use crate::zones::zone_file::{CustomProp, CustomPropsComponent};
+
+use super::asset_path_ui::asset_path_ui;
+use super::doodad::DoodadComponent;
+use super::doodad_reference::DoodadReferenceResource;
+
+/// Edits the custom props of a doodad with one widget per entry of its prop schema, and lists
+/// the props that break the schema.  Doodads without a schema only get the reflect inspector.
+pub(crate) fn custom_prop_schema_ui(world: &mut World, entity: Entity, ui: &mut egui::Ui) {
+    let Some(definition) = world
+        .get::<DoodadComponent>(entity)
+        .map(|doodad| doodad.definition.clone())
+    else {
+        return;
+    };
+    let Some(prop_schema) = &definition.prop_schema else {
+        return;
+    };
+
+    let original_props = world
+        .get::<CustomPropsComponent>(entity)
+        .map(|component| component.props.clone())
+        .unwrap_or_default();
+    let mut props = original_props.clone();
+    let mut pick_reference: Option<String> = None;
+
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let type_registry = type_registry.read();
+
+    egui::CollapsingHeader::new("Custom Props")
+        .id_source(("custom_prop_schema", entity))
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid::new(("custom_prop_schema_grid", entity))
+                .num_columns(3)
+                .show(ui, |ui| {
+                    for schema in prop_schema.iter() {
+                        let label = match schema.required {
+                            true => format!("{} *", schema.key),
+                            false => schema.key.clone(),
+                        };
+                        let label_response = ui.label(label);
+                        if let Some(description) = &schema.description {
+                            label_response.on_hover_text(description);
+                        }
+
+                        let mut remove = false;
+
+                        match props.get_mut(&schema.key) {
+                            Some(prop) => {
+                                custom_prop_widget(ui, entity, schema, prop, &type_registry, &mut pick_reference);
+
+                                remove = !schema.required && ui.small_button("Remove").clicked();
+                            }
+                            None => {
+                                if ui.button("Add").clicked() {
+                                    props.insert(schema.key.clone(), schema.initial_value());
+                                }
+                            }
+                        }
+
+                        if remove {
+                            props.remove(&schema.key);
+                        }
+
+                        ui.end_row();
+                    }
+                });
+
+            for violation in definition.validate_custom_props(&props) {
+                let color = match violation {
+                    CustomPropViolation::UnknownKey { .. } => egui::Color32::YELLOW,
+                    _ => egui::Color32::RED,
+                };
+                ui.label(RichText::new(violation.to_string()).color(color));
+            }
+        });
+
+    if let Some(key) = pick_reference {
+        world.resource_mut::<DoodadReferenceResource>().picking = Some((entity, key));
+    }
+
+    if props == original_props {
+        return;
+    }
+
+    //edits are picked up by the doodad change tracker, so they can be undone
+    match world.get_mut::<CustomPropsComponent>(entity) {
+        Some(mut custom_props) => custom_props.props = props,
+        None => {
+            world.entity_mut(entity).insert(CustomPropsComponent { props });
+        }
+    }
+}
+
+fn custom_prop_widget(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    schema: &CustomPropSchema,
+    prop: &mut CustomProp,
+    type_registry: &TypeRegistry,
+    pick_reference: &mut Option<String>,
+) {
+    if schema.is_unset_reference(prop) {
+        ui.horizontal(|ui| {
+            ui.label("(none)");
+            if ui.small_button("Pick").clicked() {
+                *pick_reference = Some(schema.key.clone());
+            }
+        });
+        return;
+    }
+
+    if CustomPropType::of(prop) != Some(schema.prop_type) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!("{:?}", prop)).color(egui::Color32::RED));
+            if ui.small_button("Reset").clicked() {
+                *prop = schema.initial_value();
+            }
+        });
+        return;
+    }
+
+    match prop {
+        CustomProp::Float(value) => {
+            let mut drag_value = DragValue::new(value).speed(0.1);
+            if let Some((min, max)) = schema.range {
+                drag_value = drag_value.range(min..=max);
+            }
+            ui.add(drag_value);
+        }
+        CustomProp::Integer(value) => {
+            let mut drag_value = DragValue::new(value);
+            if let Some((min, max)) = schema.range {
+                drag_value = drag_value.range(min.ceil() as i32..=max.floor() as i32);
+            }
+            ui.add(drag_value);
+        }
+        CustomProp::Boolean(value) => {
+            ui.checkbox(value, "");
+        }
+        CustomProp::String(value) => match &schema.choices {
+            Some(choices) => {
+                egui::ComboBox::from_id_source(("custom_prop_choice", entity, &schema.key))
+                    .selected_text(value.as_str())
+                    .show_ui(ui, |ui| {
+                        for choice in choices.iter() {
+                            ui.selectable_value(value, choice.clone(), choice);
+                        }
+                    });
+            }
+            None => {
+                ui.text_edit_singleline(value);
+            }
+        },
+        CustomProp::Vec3(value) => {
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut value.x).speed(0.1));
+                ui.add(DragValue::new(&mut value.y).speed(0.1));
+                ui.add(DragValue::new(&mut value.z).speed(0.1));
+            });
+        }
+        CustomProp::Vec2(value) => {
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut value.x).speed(0.1));
+                ui.add(DragValue::new(&mut value.y).speed(0.1));
+            });
+        }
+        CustomProp::Color(color) => {
+            let mut rgba = [color.red, color.green, color.blue, color.alpha];
+            if ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed() {
+                *color = LinearRgba::new(rgba[0], rgba[1], rgba[2], rgba[3]);
+            }
+        }
+        CustomProp::AssetPath(asset_path) => {
+            asset_path_ui(
+                &mut asset_path.0,
+                egui::Id::new(("custom_prop_asset_path", entity, &schema.key)),
+                ui,
+            );
+        }
+        //entries can be any prop, the reflect inspector already handles that
+        CustomProp::List(_) | CustomProp::Map(_) => {
+            ui.push_id(("custom_prop_collection", entity, &schema.key), |ui| {
+                reflect_inspector::ui_for_value(prop, ui, type_registry);
+            });
+        }
+        CustomProp::DoodadReference(target) => {
+            ui.horizontal(|ui| {
+                ui.label(target.to_string());
+                if ui.small_button("Pick").clicked() {
+                    *pick_reference = Some(schema.key.clone());
+                }
+            });
+        }
+        CustomProp::EmptyProp => {
+            ui.label("(empty)");
+        }
+    }
+}
@@ -17,6 +17,7 @@ use transform_gizmo_bevy::{Gizmo,GizmoOrientation,GizmoMode};
 use crate::{
     cameras::{ActiveEditorCamera, CameraWindow, EditorCamera, EDITOR_RENDER_LAYER},
     hierarchy::HierarchyWindow,
+    zones::zone_instance::owning_zone_instance,
 };
 
 
@@ -224,6 +225,11 @@ fn apply_gizmo_component (
                 continue;
             };
 
+            //instanced doodads are edited in their source zone
+            if owning_zone_instance(world, selected).is_some() {
+                continue;
+            }
+
      
 
             world.commands().entity(selected).insert ( GizmoTarget ::default() );
@@ -11,7 +11,7 @@ use bevy_editor_pls_core::EditorEvent;
 use bevy_inspector_egui::bevy_inspector::guess_entity_name;
 use bevy_inspector_egui::bevy_inspector::hierarchy::SelectedEntities;
 use bevy_inspector_egui::egui::text::CCursorRange;
-use bevy_inspector_egui::egui::{self, ScrollArea};
+use bevy_inspector_egui::egui::{self, RichText, ScrollArea};
 
 use bevy_editor_pls_core::{
     editor_window::{EditorWindow, EditorWindowContext},
@@ -22,8 +22,11 @@ use bevy_editor_pls_core::{
 
 use crate::add::{add_ui, AddWindow, AddWindowState};
 use crate::debug_settings::DebugSettingsWindow;
+use crate::doodads::doodad::DoodadComponent;
+use crate::history::doodad_commands::despawn_with_history;
 use crate::inspector::{InspectorSelection, InspectorWindow};
-use crate::zones::{ZoneComponent, ZoneEvent};
+use crate::zones::zone_gltf_export::export_zone_to_gltf;
+use crate::zones::{ZoneComponent, ZoneDirty, ZoneEvent, ZoneWindow};
 
 #[derive(Component)]
 pub struct HideInEditor;
@@ -62,6 +65,21 @@ impl EditorWindow for HierarchyWindow {
                     inspector_state.selected = InspectorSelection::Entities;
                 }
             });
+
+        //reported in the Zones window, like its own export button
+        if let Some(zone_entity) = hierarchy_state.gltf_export_request.take() {
+            let export_result = export_zone_to_gltf(world, zone_entity);
+            if let Some(zone_window_state) = cx.state_mut::<ZoneWindow>() {
+                zone_window_state.gltf_export_result = Some(export_result);
+            }
+        }
+
+        //zones with unsaved changes go through the same confirmation as the Zones window buttons
+        if let Some(zone_entity) = hierarchy_state.discard_request.take() {
+            if let Some(zone_window_state) = cx.state_mut::<ZoneWindow>() {
+                zone_window_state.confirm_discard = Some(zone_entity);
+            }
+        }
     }
 
     fn app_setup(app: &mut bevy::prelude::App) {
@@ -133,6 +151,9 @@ fn extract_wireframe_for_selected(editor: Extract<Res<Editor>>, mut commands: Co
 pub struct HierarchyState {
     pub selected: SelectedEntities,
     rename_info: Option<RenameInfo>,
+    //runs once the hierarchy is drawn, when the zones window state can be borrowed
+    gltf_export_request: Option<Entity>,
+    discard_request: Option<Entity>,
 }
 
 pub struct RenameInfo {
@@ -156,6 +177,8 @@ impl<'a> Hierarchy<'a> {
         let HierarchyState {
             selected,
             rename_info,
+            gltf_export_request,
+            discard_request,
         } = self.state;
 
         let new_selection = bevy_inspector_egui::bevy_inspector::hierarchy::Hierarchy {
@@ -165,12 +188,14 @@ impl<'a> Hierarchy<'a> {
             selected,
             context_menu: Some(&mut |ui, entity, world, rename_info| {
                 let entity_is_zone = world.entity(entity).get::<ZoneComponent>().is_some();
+                let entity_is_doodad = world.entity(entity).contains::<DoodadComponent>();
 
                 if ui.button("Despawn").clicked() {
                     despawn_recursive = Some(entity);
                 }
 
-                if ui.button("Remove keeping children").clicked() {
+                //doodad removal has to go through the history, which always takes the children along
+                if !entity_is_doodad && ui.button("Remove keeping children").clicked() {
                     despawn = Some(entity);
                 }
 
@@ -193,6 +218,42 @@ impl<'a> Hierarchy<'a> {
                         world.send_event::<ZoneEvent>(ZoneEvent::SaveZoneToFile(entity).into());
                         ui.close_menu();
                     }
+                    if ui.button("Export zone to glTF").clicked() {
+                        *gltf_export_request = Some(entity);
+                        ui.close_menu();
+                    }
+                    let entity_is_dirty = world.entity(entity).contains::<ZoneDirty>();
+                    let discard_hint = "Unsaved changes, confirm in the Zones window";
+
+                    let reload_button = ui.button("Reload zone from disk");
+                    let reload_button = match entity_is_dirty {
+                        true => reload_button.on_hover_text(discard_hint),
+                        false => reload_button,
+                    };
+                    if reload_button.clicked() {
+                        match entity_is_dirty {
+                            true => *discard_request = Some(entity),
+                            false => {
+                                world.send_event::<ZoneEvent>(ZoneEvent::ReloadZoneFromDisk(entity).into());
+                            }
+                        };
+                        ui.close_menu();
+                    }
+
+                    let unload_button = ui.button("Unload zone");
+                    let unload_button = match entity_is_dirty {
+                        true => unload_button.on_hover_text(discard_hint),
+                        false => unload_button,
+                    };
+                    if unload_button.clicked() {
+                        match entity_is_dirty {
+                            true => *discard_request = Some(entity),
+                            false => {
+                                world.send_event::<ZoneEvent>(ZoneEvent::UnloadZone(entity).into());
+                            }
+                        };
+                        ui.close_menu();
+                    }
                 }
 
                 if let Some(add_state) = self.add_state {
@@ -205,6 +266,10 @@ impl<'a> Hierarchy<'a> {
                 }
             }),
             shortcircuit_entity: Some(&mut |ui, entity, world, rename_info| {
+                if world.entity(entity).contains::<ZoneDirty>() {
+                    ui.label(RichText::new("* unsaved changes").small().color(egui::Color32::YELLOW));
+                }
+
                 if let Some(rename_info) = rename_info {
                     if rename_info.renaming && rename_info.entity == entity {
                         rename_entity_ui(ui, rename_info, world);
@@ -219,7 +284,7 @@ impl<'a> Hierarchy<'a> {
         .show::<Without<HideInEditor>>(ui);
 
         if let Some(entity) = despawn_recursive {
-            bevy::hierarchy::despawn_with_children_recursive(self.world, entity);
+            despawn_with_history(self.world, &[entity]);
         }
         if let Some(entity) = despawn {
             self.world.entity_mut(entity).despawn();
@@ -315,13 +380,12 @@ pub fn listen_for_select_entities_events(
                    let state = editor.window_state_mut::<HierarchyWindow>().unwrap();
 
                 //if ui.input(|input| input.key_pressed(egui::Key::Delete)) {
-                    for entity in  state.selected.iter() {
-                        if let Some(cmd) = commands.get_entity( entity ){
+                    let entities: Vec<Entity> = state.selected.iter().collect();
+
+                    commands.add(move |world: &mut World| {
+                        despawn_with_history(world, &entities);
+                    });
 
-                            cmd.despawn_recursive();
-                        }
-                   
-                    }
                      state.selected.clear();
                 //}
 
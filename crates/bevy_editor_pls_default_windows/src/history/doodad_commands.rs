@@ -0,0 +1,613 @@
+use bevy::hierarchy::despawn_with_children_recursive;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_editor_pls_core::Editor;
+
+use crate::doodads::doodad::DoodadComponent;
+use crate::doodads::doodad_id::DoodadId;
+use crate::doodads::DoodadProto;
+use crate::zones::zone_file::{CustomPropsComponent, CustomPropsMap, ZoneMetadata, ZoneMetadataComponent};
+use crate::zones::zone_instance::ZoneInstanceComponent;
+use crate::zones::{ZoneComponent, ZoneEvent, ZoneFilePath, ZoneResource};
+
+use super::{CommandGroup, EditorCommand, EditorHistory, EntityRemaps};
+
+/// Everything needed to respawn a doodad (or a zone and its doodads) after it was despawned.
+#[derive(Clone, Debug)]
+pub struct DoodadSnapshot {
+    pub entity: Entity,
+    pub name: String,
+    pub transform: Transform,
+    pub custom_props: Option<CustomPropsMap>,
+    pub id: Option<DoodadId>,
+    pub is_zone: bool,
+    pub zone_metadata: Option<ZoneMetadata>,
+    pub zone_file_path: Option<ZoneFilePath>,
+    //set for zone instance nodes, whose contents are respawned from the instanced zone file
+    pub zone_instance: Option<ZoneInstanceComponent>,
+    pub children: Vec<DoodadSnapshot>,
+}
+
+impl DoodadSnapshot {
+    /// Returns None for entities that are neither doodads, zones nor zone instance nodes.
+    pub fn capture(world: &World, entity: Entity) -> Option<Self> {
+        let entity_ref = world.get_entity(entity)?;
+
+        let is_zone = entity_ref.contains::<ZoneComponent>();
+        let is_doodad = entity_ref.contains::<DoodadComponent>() || entity_ref.contains::<DoodadProto>();
+        let zone_instance = entity_ref.get::<ZoneInstanceComponent>().cloned();
+
+        if !is_zone && !is_doodad && zone_instance.is_none() {
+            return None;
+        }
+
+        let name = entity_ref
+            .get::<Name>()
+            .map(|n| n.as_str().to_string())
+            .unwrap_or_default();
+
+        let transform = entity_ref.get::<Transform>().cloned().unwrap_or_default();
+
+        let custom_props = entity_ref
+            .get::<CustomPropsComponent>()
+            .map(|comp| comp.props.clone());
+
+        let id = entity_ref.get::<DoodadId>().copied();
+
+        let zone_metadata = entity_ref
+            .get::<ZoneMetadataComponent>()
+            .map(|comp| comp.metadata.clone());
+        let zone_file_path = entity_ref.get::<ZoneFilePath>().cloned();
+
+        //model scenes are rebuilt from the manifest so only nested doodads are kept,
+        //and instanced doodads are read from their zone file again
+        let children = match zone_instance.is_some() {
+            true => Vec::new(),
+            false => entity_ref
+                .get::<Children>()
+                .map(|children| {
+                    children
+                        .iter()
+                        .filter_map(|child| Self::capture(world, *child))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        Some(Self {
+            entity,
+            name,
+            transform,
+            custom_props,
+            id,
+            is_zone,
+            zone_metadata,
+            zone_file_path,
+            zone_instance,
+            children,
+        })
+    }
+
+    fn spawn(&mut self, world: &mut World, parent: Option<Entity>, remaps: &mut EntityRemaps) -> Entity {
+        let mut entity_mut = world.spawn(SpatialBundle {
+            transform: self.transform,
+            ..default()
+        });
+        entity_mut.insert(Name::new(self.name.clone()));
+
+        if self.is_zone {
+            entity_mut.insert(ZoneComponent {});
+            if let Some(metadata) = &self.zone_metadata {
+                entity_mut.insert(ZoneMetadataComponent {
+                    metadata: metadata.clone(),
+                });
+            }
+            if let Some(file_path) = &self.zone_file_path {
+                entity_mut.insert(file_path.clone());
+            }
+        } else if let Some(zone_instance) = &self.zone_instance {
+            entity_mut.insert(zone_instance.clone());
+        } else {
+            entity_mut.insert(DoodadProto).insert(CustomPropsComponent {
+                props: self.custom_props.clone().unwrap_or_default(),
+            });
+        }
+
+        //undoing a despawn brings back the same doodad, so it keeps its id
+        if let Some(id) = self.id {
+            entity_mut.insert(id);
+        }
+
+        let spawned = entity_mut.id();
+
+        if let Some(parent) = parent {
+            if world.get_entity(parent).is_some() {
+                world.entity_mut(parent).add_child(spawned);
+            }
+        }
+
+        remaps.push((self.entity, spawned));
+
+        //once parented, so the instance finds its host zone
+        if let Some(zone_instance) = &self.zone_instance {
+            world.send_event(ZoneEvent::RefreshZoneInstances(zone_instance.zone_name.clone()));
+        }
+
+        let mut zone_resource = world.resource_mut::<ZoneResource>();
+        if zone_resource.primary_zone == Some(self.entity) {
+            zone_resource.primary_zone = Some(spawned);
+        }
+
+        self.entity = spawned;
+
+        for child in self.children.iter_mut() {
+            child.spawn(world, Some(spawned), remaps);
+        }
+
+        spawned
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+        for child in self.children.iter_mut() {
+            child.remap_entity(from, to);
+        }
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.entity == entity || self.children.iter().any(|c| c.refers_to(entity))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SnapshotEntry {
+    pub parent: Option<Entity>,
+    pub snapshot: DoodadSnapshot,
+}
+
+impl SnapshotEntry {
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.parent == Some(from) {
+            self.parent = Some(to);
+        }
+        self.snapshot.remap_entity(from, to);
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.parent == Some(entity) || self.snapshot.refers_to(entity)
+    }
+}
+
+fn despawn_entries(world: &mut World, entries: &[SnapshotEntry]) {
+    for entry in entries.iter() {
+        if world.get_entity(entry.snapshot.entity).is_some() {
+            despawn_with_children_recursive(world, entry.snapshot.entity);
+        }
+    }
+}
+
+fn respawn_entries(world: &mut World, entries: &mut [SnapshotEntry], remaps: &mut EntityRemaps) {
+    for entry in entries.iter_mut() {
+        entry.snapshot.spawn(world, entry.parent, remaps);
+    }
+}
+
+fn describe_entries(verb: &str, entries: &[SnapshotEntry]) -> String {
+    match entries {
+        [entry] => format!("{} {}", verb, entry.snapshot.name),
+        _ => format!("{} {} entities", verb, entries.len()),
+    }
+}
+
+pub struct SpawnDoodadsCommand {
+    pub entries: Vec<SnapshotEntry>,
+}
+
+impl EditorCommand for SpawnDoodadsCommand {
+    fn label(&self) -> String {
+        describe_entries("Spawn", &self.entries)
+    }
+
+    fn undo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        despawn_entries(world, &self.entries);
+    }
+
+    fn redo(&mut self, world: &mut World, remaps: &mut EntityRemaps) {
+        respawn_entries(world, &mut self.entries, remaps);
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        for entry in self.entries.iter_mut() {
+            entry.remap_entity(from, to);
+        }
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.entries.iter().any(|e| e.refers_to(entity))
+    }
+}
+
+pub struct DespawnDoodadsCommand {
+    pub entries: Vec<SnapshotEntry>,
+}
+
+impl EditorCommand for DespawnDoodadsCommand {
+    fn label(&self) -> String {
+        describe_entries("Despawn", &self.entries)
+    }
+
+    fn undo(&mut self, world: &mut World, remaps: &mut EntityRemaps) {
+        respawn_entries(world, &mut self.entries, remaps);
+    }
+
+    fn redo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        despawn_entries(world, &self.entries);
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        for entry in self.entries.iter_mut() {
+            entry.remap_entity(from, to);
+        }
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.entries.iter().any(|e| e.refers_to(entity))
+    }
+}
+
+/// Despawns the entities recursively and records the doodads and zones among them so they can be restored.
+pub fn despawn_with_history(world: &mut World, entities: &[Entity]) {
+    //skip entities whose ancestor is also being despawned, they are captured with the ancestor
+    let roots: Vec<Entity> = entities
+        .iter()
+        .copied()
+        .filter(|entity| {
+            let mut current = *entity;
+            while let Some(parent) = world.get::<Parent>(current) {
+                current = parent.get();
+                if entities.contains(&current) {
+                    return false;
+                }
+            }
+            true
+        })
+        .collect();
+
+    let mut entries = Vec::new();
+    for entity in roots.iter() {
+        if let Some(snapshot) = DoodadSnapshot::capture(world, *entity) {
+            entries.push(SnapshotEntry {
+                parent: world.get::<Parent>(*entity).map(|p| p.get()),
+                snapshot,
+            });
+        }
+    }
+
+    for entity in roots {
+        if world.get_entity(entity).is_some() {
+            despawn_with_children_recursive(world, entity);
+        }
+    }
+
+    if !entries.is_empty() {
+        world
+            .resource_mut::<EditorHistory>()
+            .push(DespawnDoodadsCommand { entries });
+    }
+}
+
+// --------------------------------------------------------
+
+pub struct TransformCommand {
+    pub entity: Entity,
+    pub before: Transform,
+    pub after: Transform,
+}
+
+impl TransformCommand {
+    fn apply(&self, world: &mut World, transform: Transform) {
+        if let Some(mut xform) = world.get_mut::<Transform>(self.entity) {
+            *xform = transform;
+        }
+        DoodadChangeTracker::sync(world, self.entity);
+    }
+}
+
+impl EditorCommand for TransformCommand {
+    fn label(&self) -> String {
+        "Transform doodad".to_string()
+    }
+
+    fn undo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, self.before);
+    }
+
+    fn redo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, self.after);
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.entity == entity
+    }
+}
+
+pub struct CustomPropsCommand {
+    pub entity: Entity,
+    pub before: Option<CustomPropsMap>,
+    pub after: Option<CustomPropsMap>,
+}
+
+impl CustomPropsCommand {
+    fn apply(&self, world: &mut World, props: Option<CustomPropsMap>) {
+        let Some(mut entity_mut) = world.get_entity_mut(self.entity) else {
+            return;
+        };
+
+        match props {
+            Some(props) => {
+                entity_mut.insert(CustomPropsComponent { props });
+            }
+            None => {
+                entity_mut.remove::<CustomPropsComponent>();
+            }
+        }
+        DoodadChangeTracker::sync(world, self.entity);
+    }
+}
+
+impl EditorCommand for CustomPropsCommand {
+    fn label(&self) -> String {
+        "Edit custom props".to_string()
+    }
+
+    fn undo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, self.before.clone());
+    }
+
+    fn redo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, self.after.clone());
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.entity == entity
+    }
+}
+
+pub struct ReparentCommand {
+    pub entity: Entity,
+    pub before: Option<Entity>,
+    pub after: Option<Entity>,
+}
+
+impl ReparentCommand {
+    fn apply(&self, world: &mut World, parent: Option<Entity>) {
+        if world.get_entity(self.entity).is_none() {
+            return;
+        }
+
+        match parent.filter(|p| world.get_entity(*p).is_some()) {
+            Some(parent) => {
+                world.entity_mut(self.entity).set_parent(parent);
+            }
+            None => {
+                world.entity_mut(self.entity).remove_parent();
+            }
+        }
+        DoodadChangeTracker::sync(world, self.entity);
+    }
+}
+
+impl EditorCommand for ReparentCommand {
+    fn label(&self) -> String {
+        "Reparent doodad".to_string()
+    }
+
+    fn undo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, self.before);
+    }
+
+    fn redo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, self.after);
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+        if self.before == Some(from) {
+            self.before = Some(to);
+        }
+        if self.after == Some(from) {
+            self.after = Some(to);
+        }
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.entity == entity || self.before == Some(entity) || self.after == Some(entity)
+    }
+}
+
+// --------------------------------------------------------
+
+#[derive(Clone, PartialEq)]
+struct TrackedDoodadState {
+    transform: Transform,
+    custom_props: Option<CustomPropsMap>,
+    parent: Option<Entity>,
+}
+
+impl TrackedDoodadState {
+    fn read(world: &World, entity: Entity) -> Option<Self> {
+        let entity_ref = world.get_entity(entity)?;
+
+        Some(Self {
+            transform: *entity_ref.get::<Transform>()?,
+            custom_props: entity_ref.get::<CustomPropsComponent>().map(|c| c.props.clone()),
+            parent: entity_ref.get::<Parent>().map(|p| p.get()),
+        })
+    }
+}
+
+/// Remembers the last committed state of every doodad so that gizmo drags, grid locks,
+/// inspector edits and reparenting can be turned into history commands.
+#[derive(Resource, Default)]
+pub struct DoodadChangeTracker {
+    tracked: HashMap<Entity, TrackedDoodadState>,
+    pending: HashSet<Entity>,
+}
+
+impl DoodadChangeTracker {
+    /// Commands call this after writing to an entity so the write is not recorded as a new edit.
+    pub fn sync(world: &mut World, entity: Entity) {
+        let state = TrackedDoodadState::read(world, entity);
+        let mut tracker = world.resource_mut::<DoodadChangeTracker>();
+
+        if let Some(state) = state {
+            if tracker.tracked.contains_key(&entity) {
+                tracker.tracked.insert(entity, state);
+            }
+        }
+        tracker.pending.remove(&entity);
+    }
+}
+
+pub fn track_doodad_changes(
+    mut tracker: ResMut<DoodadChangeTracker>,
+    mut history: ResMut<EditorHistory>,
+
+    changed_query: Query<
+        Entity,
+        (
+            With<DoodadComponent>,
+            Or<(
+                Changed<Transform>,
+                Changed<CustomPropsComponent>,
+                Changed<Parent>,
+            )>,
+        ),
+    >,
+    doodad_query: Query<
+        (&Transform, Option<&CustomPropsComponent>, Option<&Parent>),
+        With<DoodadComponent>,
+    >,
+
+    mut removed_doodads: RemovedComponents<DoodadComponent>,
+    mut removed_parents: RemovedComponents<Parent>,
+
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    editor: Res<Editor>,
+) {
+    for entity in removed_doodads.read() {
+        tracker.tracked.remove(&entity);
+        tracker.pending.remove(&entity);
+    }
+
+    let changed_entities: Vec<Entity> = changed_query
+        .iter()
+        .chain(removed_parents.read().filter(|e| doodad_query.contains(*e)))
+        .collect();
+
+    for entity in changed_entities {
+        let Ok((xform, custom_props_comp, parent)) = doodad_query.get(entity) else {
+            continue;
+        };
+
+        if !tracker.tracked.contains_key(&entity) {
+            //first time we see this doodad, this is not an edit
+            tracker.tracked.insert(
+                entity,
+                TrackedDoodadState {
+                    transform: *xform,
+                    custom_props: custom_props_comp.map(|c| c.props.clone()),
+                    parent: parent.map(|p| p.get()),
+                },
+            );
+            continue;
+        }
+
+        tracker.pending.insert(entity);
+    }
+
+    //wait for drags and text edits to finish so they become a single step
+    if mouse_input.pressed(MouseButton::Left) || editor.listening_for_text() {
+        return;
+    }
+
+    if tracker.pending.is_empty() {
+        return;
+    }
+
+    let pending: Vec<Entity> = tracker.pending.drain().collect();
+    let mut commands: Vec<Box<dyn EditorCommand>> = Vec::new();
+    let mut edited_count = 0;
+
+    for entity in pending {
+        let Ok((xform, custom_props_comp, parent)) = doodad_query.get(entity) else {
+            continue;
+        };
+
+        let current = TrackedDoodadState {
+            transform: *xform,
+            custom_props: custom_props_comp.map(|c| c.props.clone()),
+            parent: parent.map(|p| p.get()),
+        };
+
+        let Some(previous) = tracker.tracked.insert(entity, current.clone()) else {
+            continue;
+        };
+
+        if previous == current {
+            continue;
+        }
+
+        edited_count += 1;
+
+        if previous.parent != current.parent {
+            commands.push(Box::new(ReparentCommand {
+                entity,
+                before: previous.parent,
+                after: current.parent,
+            }));
+        }
+        if previous.transform != current.transform {
+            commands.push(Box::new(TransformCommand {
+                entity,
+                before: previous.transform,
+                after: current.transform,
+            }));
+        }
+        if previous.custom_props != current.custom_props {
+            commands.push(Box::new(CustomPropsCommand {
+                entity,
+                before: previous.custom_props,
+                after: current.custom_props,
+            }));
+        }
+    }
+
+    match commands.len() {
+        0 => {}
+        1 => history.push_boxed(commands.remove(0)),
+        _ => {
+            let label = match edited_count {
+                1 => "Edit doodad".to_string(),
+                n => format!("Edit {} doodads", n),
+            };
+            history.push(CommandGroup { label, commands });
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use bevy::prelude::*;
+use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
+use bevy_inspector_egui::egui::{self, RichText, ScrollArea};
+
+pub mod doodad_commands;
+
+/*
+
+Editor-wide undo / redo.
+
+Every reversible edit is pushed onto the EditorHistory as a boxed EditorCommand.
+Commands get full World access when they are undone or redone, so other crates
+(terrain brushes, regions ...) can implement their own commands and share the same stack.
+
+*/
+
+/// Pairs of (old entity, respawned entity) produced while undoing or redoing a command.
+pub type EntityRemaps = Vec<(Entity, Entity)>;
+
+pub trait EditorCommand: Send + Sync + 'static {
+    fn label(&self) -> String;
+
+    fn undo(&mut self, world: &mut World, remaps: &mut EntityRemaps);
+
+    fn redo(&mut self, world: &mut World, remaps: &mut EntityRemaps);
+
+    /// Called when another command respawned an entity that this command refers to.
+    fn remap_entity(&mut self, _from: Entity, _to: Entity) {}
+
+    /// Whether undoing or redoing this command touches the entity.
+    fn refers_to(&self, _entity: Entity) -> bool {
+        false
+    }
+
+    /// Approximate heap size in bytes, counted against the history memory budget.
+    fn memory_size(&self) -> usize {
+        0
+    }
+}
+
+/// Several commands that are undone and redone as a single step.
+pub struct CommandGroup {
+    pub label: String,
+    pub commands: Vec<Box<dyn EditorCommand>>,
+}
+
+impl EditorCommand for CommandGroup {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn undo(&mut self, world: &mut World, remaps: &mut EntityRemaps) {
+        for i in (0..self.commands.len()).rev() {
+            let mut step_remaps = Vec::new();
+            self.commands[i].undo(world, &mut step_remaps);
+            self.apply_remaps(i, &step_remaps);
+            remaps.extend(step_remaps);
+        }
+    }
+
+    fn redo(&mut self, world: &mut World, remaps: &mut EntityRemaps) {
+        for i in 0..self.commands.len() {
+            let mut step_remaps = Vec::new();
+            self.commands[i].redo(world, &mut step_remaps);
+            self.apply_remaps(i, &step_remaps);
+            remaps.extend(step_remaps);
+        }
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        for command in self.commands.iter_mut() {
+            command.remap_entity(from, to);
+        }
+    }
+
+    fn refers_to(&self, entity: Entity) -> bool {
+        self.commands.iter().any(|c| c.refers_to(entity))
+    }
+
+    fn memory_size(&self) -> usize {
+        self.commands.iter().map(|c| c.memory_size()).sum()
+    }
+}
+
+impl CommandGroup {
+    //the command that produced the remaps has already updated itself
+    fn apply_remaps(&mut self, source_index: usize, remaps: &EntityRemaps) {
+        for (i, command) in self.commands.iter_mut().enumerate() {
+            if i == source_index {
+                continue;
+            }
+            for (from, to) in remaps.iter() {
+                command.remap_entity(*from, *to);
+            }
+        }
+    }
+}
+
+pub const DEFAULT_HISTORY_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+#[derive(Resource)]
+pub struct EditorHistory {
+    undo_stack: Vec<Box<dyn EditorCommand>>,
+    redo_stack: Vec<Box<dyn EditorCommand>>,
+
+    /// Oldest undo entries are dropped once the stacks grow past this many bytes.
+    pub memory_budget_bytes: usize,
+}
+
+impl Default for EditorHistory {
+    fn default() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            memory_budget_bytes: DEFAULT_HISTORY_MEMORY_BUDGET,
+        }
+    }
+}
+
+impl EditorHistory {
+    pub fn push(&mut self, command: impl EditorCommand) {
+        self.push_boxed(Box::new(command));
+    }
+
+    pub fn push_boxed(&mut self, command: Box<dyn EditorCommand>) {
+        self.redo_stack.clear();
+        self.undo_stack.push(command);
+        self.enforce_memory_budget();
+    }
+
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        self.memory_budget_bytes = bytes;
+        self.enforce_memory_budget();
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.undo_stack
+            .iter()
+            .chain(self.redo_stack.iter())
+            .map(|c| c.memory_size())
+            .sum()
+    }
+
+    //always keeps the most recent entry, even if it alone is over budget
+    fn enforce_memory_budget(&mut self) {
+        let mut usage = self.memory_usage();
+
+        while usage > self.memory_budget_bytes && self.undo_stack.len() > 1 {
+            let evicted = self.undo_stack.remove(0);
+            usage -= evicted.memory_size();
+            info!("history over memory budget, dropped {}", evicted.label());
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Oldest first
+    pub fn undo_labels(&self) -> Vec<String> {
+        self.undo_stack.iter().map(|c| c.label()).collect()
+    }
+
+    /// Next redo first
+    pub fn redo_labels(&self) -> Vec<String> {
+        self.redo_stack.iter().rev().map(|c| c.label()).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Drops every entry that refers to one of the entities, used when they are despawned outside of the history.
+    /// Entries further from the present than a dropped one depend on it, so they are dropped too.
+    pub fn forget_entities(&mut self, entities: &[Entity]) {
+        let last_referring_entry = |stack: &[Box<dyn EditorCommand>]| {
+            stack
+                .iter()
+                .rposition(|command| entities.iter().any(|e| command.refers_to(*e)))
+        };
+
+        if let Some(index) = last_referring_entry(&self.undo_stack) {
+            let dropped = self.undo_stack.drain(..=index).count();
+            info!("dropped {} undo entries of despawned entities", dropped);
+        }
+
+        //the next redo is the last element
+        if let Some(index) = last_referring_entry(&self.redo_stack) {
+            let dropped = self.redo_stack.drain(..=index).count();
+            info!("dropped {} redo entries of despawned entities", dropped);
+        }
+    }
+
+    fn remap_entities(&mut self, remaps: &EntityRemaps) {
+        for command in self.undo_stack.iter_mut().chain(self.redo_stack.iter_mut()) {
+            for (from, to) in remaps.iter() {
+                command.remap_entity(*from, *to);
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub enum HistoryEvent {
+    Undo,
+    Redo,
+    Clear,
+}
+
+pub fn apply_history_events(world: &mut World) {
+    let events: Vec<HistoryEvent> = world
+        .resource_mut::<Events<HistoryEvent>>()
+        .drain()
+        .collect();
+
+    for evt in events {
+        match evt {
+            HistoryEvent::Undo => undo(world),
+            HistoryEvent::Redo => redo(world),
+            HistoryEvent::Clear => world.resource_mut::<EditorHistory>().clear(),
+        }
+    }
+}
+
+fn undo(world: &mut World) {
+    let Some(mut command) = world.resource_mut::<EditorHistory>().undo_stack.pop() else {
+        return;
+    };
+
+    info!("undo {}", command.label());
+
+    let mut remaps = Vec::new();
+    command.undo(world, &mut remaps);
+
+    let mut history = world.resource_mut::<EditorHistory>();
+    history.remap_entities(&remaps);
+    history.redo_stack.push(command);
+}
+
+fn redo(world: &mut World) {
+    let Some(mut command) = world.resource_mut::<EditorHistory>().redo_stack.pop() else {
+        return;
+    };
+
+    info!("redo {}", command.label());
+
+    let mut remaps = Vec::new();
+    command.redo(world, &mut remaps);
+
+    let mut history = world.resource_mut::<EditorHistory>();
+    history.remap_entities(&remaps);
+    history.undo_stack.push(command);
+}
+
+// --------------------------------------------------------
+
+pub struct HistoryWindow;
+
+impl EditorWindow for HistoryWindow {
+    type State = ();
+    const NAME: &'static str = "History";
+
+    fn ui(world: &mut World, _cx: EditorWindowContext, ui: &mut egui::Ui) {
+        let history = world.resource::<EditorHistory>();
+
+        let can_undo = history.can_undo();
+        let can_redo = history.can_redo();
+        let undo_labels = history.undo_labels();
+        let redo_labels = history.redo_labels();
+        let memory_usage_mb = history.memory_usage() as f32 / (1024.0 * 1024.0);
+        let memory_budget_mb = history.memory_budget_bytes as f32 / (1024.0 * 1024.0);
+
+        let mut events_to_send = Vec::new();
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(can_undo, egui::Button::new("Undo (Ctrl+Z)"))
+                .clicked()
+            {
+                events_to_send.push(HistoryEvent::Undo);
+            }
+            if ui
+                .add_enabled(can_redo, egui::Button::new("Redo (Ctrl+Shift+Z)"))
+                .clicked()
+            {
+                events_to_send.push(HistoryEvent::Redo);
+            }
+            if ui.button("Clear").clicked() {
+                events_to_send.push(HistoryEvent::Clear);
+            }
+        });
+
+        ui.label(format!(
+            "Memory: {:.1} / {:.0} MB",
+            memory_usage_mb, memory_budget_mb
+        ));
+
+        ui.separator();
+
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                //redo entries are shown greyed out above the current state
+                for (i, label) in redo_labels.iter().enumerate().rev() {
+                    if ui
+                        .selectable_label(false, RichText::new(label).weak())
+                        .clicked()
+                    {
+                        events_to_send.extend((0..=i).map(|_| HistoryEvent::Redo));
+                    }
+                }
+
+                for (i, label) in undo_labels.iter().enumerate().rev() {
+                    let is_current = i + 1 == undo_labels.len();
+
+                    if ui.selectable_label(is_current, label).clicked() {
+                        //undo everything after the clicked entry
+                        let steps = undo_labels.len() - 1 - i;
+                        events_to_send.extend((0..steps).map(|_| HistoryEvent::Undo));
+                    }
+                }
+
+                if undo_labels.is_empty() && redo_labels.is_empty() {
+                    ui.label("No edits yet");
+                }
+            });
+
+        world.send_event_batch(events_to_send);
+    }
+}
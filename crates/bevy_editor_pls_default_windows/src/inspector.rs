@@ -1,7 +1,11 @@
 use std::any::TypeId;
 
 use super::add::{AddWindow, AddWindowState};
+use super::doodads::bulk_props_ui::bulk_custom_props_ui;
+use super::doodads::doodad_reference::doodad_references_ui;
+use super::doodads::prop_schema_ui::custom_prop_schema_ui;
 use super::hierarchy::HierarchyWindow;
+use super::zones::zone_instance::owning_zone_instance;
 use bevy::asset::UntypedAssetId;
 use bevy::prelude::{AppTypeRegistry, Entity, World};
 use bevy::reflect::TypeRegistry;
@@ -66,10 +70,23 @@ fn inspector(
                 ui.label("No entity selected");
             }
             &[entity] => {
+                if instanced_doodads_banner(world, &[entity], ui) {
+                    ui.add_enabled_ui(false, |ui| bevy_inspector::ui_for_entity(world, entity, ui));
+                    return;
+                }
+                custom_prop_schema_ui(world, entity, ui);
+                doodad_references_ui(world, entity, ui);
                 bevy_inspector::ui_for_entity(world, entity, ui);
                 add_ui(ui, &[entity], world, add_window_state);
             }
             entities => {
+                if instanced_doodads_banner(world, entities, ui) {
+                    ui.add_enabled_ui(false, |ui| {
+                        bevy_inspector::ui_for_entities_shared_components(world, entities, ui)
+                    });
+                    return;
+                }
+                bulk_custom_props_ui(world, entities, ui);
                 bevy_inspector::ui_for_entities_shared_components(world, entities, ui);
                 add_ui(ui, entities, world, add_window_state);
             }
@@ -85,6 +102,32 @@ fn inspector(
     });
 }
 
+//entities spawned by a zone instance are shown read-only, edits would never be saved
+fn instanced_doodads_banner(world: &World, entities: &[Entity], ui: &mut egui::Ui) -> bool {
+    let mut source_zones: Vec<String> = entities
+        .iter()
+        .filter_map(|entity| owning_zone_instance(world, *entity))
+        .map(|zone_instance| zone_instance.zone_name.clone())
+        .collect();
+    source_zones.sort();
+    source_zones.dedup();
+
+    if source_zones.is_empty() {
+        return false;
+    }
+
+    ui.label(
+        egui::RichText::new(format!(
+            "Part of a zone instance, edit the source zone to change it: {}",
+            source_zones.join(", ")
+        ))
+        .color(egui::Color32::YELLOW),
+    );
+    ui.separator();
+
+    true
+}
+
 fn add_ui(
     ui: &mut egui::Ui,
     entities: &[Entity],
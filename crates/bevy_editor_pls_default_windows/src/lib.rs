@@ -4,7 +4,7 @@
 use bevy::prelude::*;
  
 use doodads::DoodadPlugin;
-use zones::{zone_file::{CustomProp, CustomPropsComponent},  ZoneEvent, ZoneResource};
+use zones::{ZoneEvent, ZoneResource};
 
  use transform_gizmo_bevy::TransformGizmoPlugin;
 
@@ -22,8 +22,11 @@ pub mod resources;
 pub mod scenes;
 pub mod lighting;
 
+pub mod history;
+
 pub mod doodads;
 pub mod placement;
+pub mod project;
 pub mod zones;
 
 pub struct StandardWindowsPlugin {}
@@ -33,13 +36,24 @@ impl Plugin for StandardWindowsPlugin {
          app
             .add_event::<placement::PlacementEvent>()
             .add_event::<ZoneEvent>()
+            .add_event::<zones::ZoneSaveFailed>()
            
-            .register_type::<CustomPropsComponent>() //reflect
-              .register_type::<CustomProp>() //reflect
             .add_event::<doodads::picking::SelectDoodadEvent>()
             .init_resource::<ZoneResource>()
+            .init_resource::<zones::zone_migration::ZoneMigrationResource>()
+            .init_resource::<zones::zone_prop_validation::ZonePropValidationResource>()
+            .init_resource::<zones::zone_file_watcher::ZoneFileWatcher>()
+            .init_resource::<zones::zone_instance::ZoneInstanceResource>()
             .init_resource::<placement::PlacementResource>()
-            .add_systems(Update, zones::handle_zone_events)
+            //reloading in place despawns doodads, mark_dirty_zones has to see the ReloadedZone marker
+            .add_systems(Update, zones::handle_zone_events.before(zones::mark_dirty_zones))
+            .add_systems(Update, zones::mark_dirty_zones)
+            .add_systems(Update, zones::zone_file_watcher::watch_zone_files)
+
+            .add_event::<history::HistoryEvent>()
+            .init_resource::<history::EditorHistory>()
+            .init_resource::<history::doodad_commands::DoodadChangeTracker>()
+            .add_systems(Update, (history::apply_history_events, history::doodad_commands::track_doodad_changes).chain())
 
 
             .add_plugins(DoodadPlugin {})
@@ -227,10 +227,15 @@ pub fn handle_placement_tool_events(
                         PlaceDoodadEvent {
                              position: simple_xform.translation, 
                              scale: Some(simple_xform.scale), 
-                             rotation_euler: Some(simple_xform.rotation), 
+                             rotation_euler: None, 
+                             rotation: Some(simple_xform.rotation_quat), 
                              doodad_name: name_comp.to_string().clone(),
                              custom_props: None ,
-                             zone: None 
+                             zone: None,
+                             parent: None,
+                             reserved_entity: None,
+                             //clones get their own id
+                             id: None,
                       });
 
 
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+/*
+
+A project describes where a game's content lives, so one editor binary can edit several games.
+
+Every path in here is relative to the project root.  The editor changes its working directory
+to the project root on startup (see the main crate), so these paths resolve the same way the
+old hardcoded `assets/...` paths did.  Manifest paths are relative to the asset folder since
+they are also loaded through the asset server.
+
+*/
+
+pub const PROJECT_FILE_EXTENSION: &str = "editorproject.ron";
+
+static EDITOR_PROJECT: OnceLock<EditorProject> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EditorProject {
+    pub name: String,
+
+    pub asset_folder: PathBuf,
+
+    //these are relative to the asset folder
+    pub zones_folder: PathBuf,
+    pub terrain_folder: PathBuf,
+    pub regions_config: PathBuf,
+    pub doodad_manifest: PathBuf,
+    pub terrain_manifest: PathBuf,
+    pub liquid_manifest: PathBuf,
+
+    //relative to the project root, zone exports for other tools are written here
+    pub export_folder: PathBuf,
+
+    //what saving a zone does when doodad custom props break their manifest prop schema
+    pub invalid_props_on_save: InvalidPropsOnSave,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvalidPropsOnSave {
+    //save anyway and list the problems in the Zones window
+    #[default]
+    Warn,
+    //keep the zone unsaved until the props are fixed
+    Refuse,
+}
+
+impl Default for EditorProject {
+    fn default() -> Self {
+        Self {
+            name: "Untitled Project".into(),
+            asset_folder: "assets".into(),
+            zones_folder: "zones".into(),
+            terrain_folder: "terrain".into(),
+            regions_config: "regions/regions_config.ron".into(),
+            doodad_manifest: "doodad_manifest.doodadmanifest.ron".into(),
+            terrain_manifest: "terrain_manifest.terrainmanifest.ron".into(),
+            liquid_manifest: "liquid_manifest.liquidmanifest.ron".into(),
+            export_folder: "exports".into(),
+            invalid_props_on_save: InvalidPropsOnSave::Warn,
+        }
+    }
+}
+
+impl EditorProject {
+    pub fn load_from_file(
+        file_path: impl AsRef<Path>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let contents = fs::read_to_string(file_path)?;
+        let project = ron::de::from_str::<Self>(&contents)?;
+        Ok(project)
+    }
+
+    /// Finds a `*.editorproject.ron` file directly inside a folder.
+    pub fn find_project_file(folder: impl AsRef<Path>) -> Option<PathBuf> {
+        let mut project_files: Vec<PathBuf> = fs::read_dir(folder)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(false, |name| name.ends_with(PROJECT_FILE_EXTENSION))
+            })
+            .collect();
+
+        project_files.sort();
+        project_files.into_iter().next()
+    }
+
+    pub fn zones_folder_path(&self) -> PathBuf {
+        self.asset_folder.join(&self.zones_folder)
+    }
+
+    pub fn terrain_config_path(&self, terrain_name: &str) -> PathBuf {
+        self.asset_folder
+            .join(&self.terrain_folder)
+            .join(terrain_name)
+            .join("terrain_config.ron")
+    }
+
+    pub fn regions_config_path(&self) -> PathBuf {
+        self.asset_folder.join(&self.regions_config)
+    }
+
+    pub fn doodad_manifest_path(&self) -> PathBuf {
+        self.asset_folder.join(&self.doodad_manifest)
+    }
+
+    pub fn liquid_manifest_path(&self) -> PathBuf {
+        self.asset_folder.join(&self.liquid_manifest)
+    }
+
+    pub fn export_folder_path(&self) -> PathBuf {
+        self.export_folder.clone()
+    }
+}
+
+/// Sets the active project.  Must be called once at startup, before anything resolves a path.
+pub fn set_editor_project(project: EditorProject) -> Result<(), EditorProject> {
+    EDITOR_PROJECT.set(project)
+}
+
+/// The active project, or the default `assets` layout when none was set.
+pub fn editor_project() -> &'static EditorProject {
+    EDITOR_PROJECT.get_or_init(EditorProject::default)
+}
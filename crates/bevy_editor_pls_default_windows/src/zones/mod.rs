@@ -1,11 +1,19 @@
 use std::fs;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
+use bevy::render::primitives::Aabb;
+use bevy_editor_pls_core::{editor_window::{EditorWindow, EditorWindowContext}, Editor};
 use bevy_inspector_egui::egui::{self, RichText};
 
 use std::path::Path;
 
+use crate::doodads::doodad::DoodadComponent;
+use crate::doodads::doodad_id::DoodadId;
 use crate::doodads::PlaceDoodadEvent;
+use crate::hierarchy::HierarchyWindow;
+use crate::history::EditorHistory;
+use crate::project::{editor_project, InvalidPropsOnSave};
+use bevy::utils::{HashMap, HashSet};
 
 #[derive(Component)]
 pub struct ZoneComponent {}
@@ -17,18 +25,80 @@ pub enum ZoneEvent {
     CreateNewZone(String),
     LoadZoneFile(String),
     ResetPrimaryZone,
+    SaveAllDirtyZones,
+    //load a zone from an autosave recovery file, replacing the loaded zone with the same name
+    RestoreZoneFromRecovery {
+        zone_name: String,
+        recovery_file: String,
+    },
+    UnloadZone(Entity),
+    //discards unsaved changes
+    ReloadZoneFromDisk(Entity),
+    AddZoneInstance {
+        zone: Entity,
+        zone_name: String,
+    },
+    //respawns every instance of a zone, after its file changed
+    RefreshZoneInstances(String),
 }
 
+/// A zone that was asked to save but is still unsaved.
+#[derive(Event, Clone, Debug)]
+pub struct ZoneSaveFailed {
+    pub zone_entity: Entity,
+    pub zone_name: String,
+    pub reason: String,
+}
+
+/// The zone file a zone was loaded from or last saved to.
+#[derive(Component, Clone, Debug)]
+pub struct ZoneFilePath(pub String);
+
+/// Zone has changes that have not been written to its zone file.
+#[derive(Component)]
+pub struct ZoneDirty;
+
+/// Zone whose doodads were just despawned to reload it in place, so the removals do not count as unsaved changes.
+#[derive(Component)]
+pub struct ReloadedZone;
+
+/// Doodads spawned while loading a zone file, so they do not count as unsaved changes.
+#[derive(Component)]
+pub struct SpawnedFromZoneFile;
+
 #[derive(Resource, Default)]
 pub struct ZoneResource {
     pub primary_zone: Option<Entity>,
 }
 
 pub mod zone_file;
+pub mod zone_migration;
+pub mod zone_file_browser;
+pub mod zone_file_watcher;
+pub mod zone_origin;
+pub mod zone_instance;
+pub mod zone_file_format;
+pub mod zone_gltf_export;
+pub mod zone_gltf_import;
+pub mod zone_prop_validation;
 
 use zone_file::ZoneFile;
-
-use self::zone_file::CustomPropsComponent;
+use zone_file_format::{read_zone_file, write_zone_file, ZoneFileFormat};
+use zone_gltf_export::{export_zone_to_gltf, ZoneGltfExport};
+use zone_gltf_import::{import_gltf_layout, ZoneGltfImport};
+use zone_file_browser::{zone_file_browser_ui, ZoneFileBrowserState};
+use zone_file_watcher::{changed_zone_files_ui, ZoneFileWatcher};
+use zone_origin::{zone_origin_ui, ZoneOriginUiState};
+use zone_instance::{
+    instance_chain, spawn_zone_instance, spawn_zone_instance_contents, zone_instances_ui,
+    ZoneInstanceComponent, ZoneInstanceResource, ZoneInstanceUiState,
+};
+use zone_migration::{UpgradedZone, ZoneMigrationResource};
+use zone_prop_validation::{
+    zone_prop_validation_ui, zone_prop_violations, InvalidZoneProps, ZonePropValidationResource,
+};
+
+use self::zone_file::{CustomPropsComponent, ZoneBounds, ZoneEntity, ZoneInstance, ZoneMetadataComponent};
 
 const DEFAULT_FILENAME: &str = "zone01";
 
@@ -40,6 +110,42 @@ pub struct ZoneWindowState {
     create_filename: String,
     load_filename: String,
     zone_create_result: Option<Result<(), Box<dyn std::error::Error + Send + Sync>>>,
+    //zone with unsaved changes waiting for confirmation before it is reloaded or unloaded
+    pub(crate) confirm_discard: Option<Entity>,
+    file_browser: ZoneFileBrowserState,
+    instances: ZoneInstanceUiState,
+    origin: ZoneOriginUiState,
+    pub(crate) gltf_export_result: Option<Result<ZoneGltfExport, Box<dyn std::error::Error + Send + Sync>>>,
+    gltf_import_path: String,
+    gltf_import_result: Option<Result<ZoneGltfImport, Box<dyn std::error::Error + Send + Sync>>>,
+}
+
+struct LoadedZoneRow {
+    entity: Entity,
+    name: String,
+    file_path: Option<String>,
+    dirty: bool,
+    entity_count: usize,
+}
+
+//counts the named descendants, which are the entities that get written to the zone file
+fn count_zone_entities(world: &World, zone_entity: Entity) -> usize {
+    let mut count = 0;
+    let mut stack: Vec<Entity> = vec![zone_entity];
+
+    while let Some(entity) = stack.pop() {
+        let Some(children) = world.get::<Children>(entity) else {
+            continue;
+        };
+        for child in children.iter() {
+            if world.get::<Name>(*child).is_some() {
+                count += 1;
+                stack.push(*child);
+            }
+        }
+    }
+
+    count
 }
 
 pub struct ZoneWindow;
@@ -138,6 +244,269 @@ impl EditorWindow for ZoneWindow {
       }); // ---- H
 
 
+        let mut loaded_zone_query = world.query_filtered::<(Entity, &Name, Option<&ZoneFilePath>, Has<ZoneDirty>), With<ZoneComponent>>();
+        let mut loaded_zones: Vec<LoadedZoneRow> = loaded_zone_query
+            .iter(world)
+            .map(|(ent, name, file_path, dirty)| LoadedZoneRow {
+                entity: ent,
+                name: name.as_str().to_owned(),
+                file_path: file_path.map(|p| p.0.clone()),
+                dirty,
+                entity_count: 0,
+            })
+            .collect();
+        loaded_zones.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for row in loaded_zones.iter_mut() {
+            row.entity_count = count_zone_entities(world, row.entity);
+        }
+
+        let dirty_count = loaded_zones.iter().filter(|z| z.dirty).count();
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(format!("Loaded zones: {}", loaded_zones.len()));
+
+            if dirty_count > 0 {
+                ui.label(
+                    RichText::new(format!("({} unsaved)", dirty_count)).color(egui::Color32::YELLOW),
+                );
+            }
+
+            if ui
+                .add_enabled(dirty_count > 0, egui::Button::new("Save All Zones"))
+                .clicked()
+            {
+                world.send_event::<ZoneEvent>(ZoneEvent::SaveAllDirtyZones);
+            }
+        });
+
+        egui::Grid::new("loaded_zones_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for row in loaded_zones.iter() {
+                    let is_primary = primary_zone_entity == Some(row.entity);
+
+                    let mut zone_label = RichText::new(match row.dirty {
+                        true => format!("* {}", row.name),
+                        false => row.name.clone(),
+                    });
+                    if is_primary {
+                        zone_label = zone_label.strong();
+                    }
+                    if row.dirty {
+                        zone_label = zone_label.color(egui::Color32::YELLOW);
+                    }
+                    ui.label(zone_label);
+
+                    ui.label(
+                        RichText::new(row.file_path.as_deref().unwrap_or("(not saved yet)")).weak(),
+                    );
+                    ui.label(format!("{} entities", row.entity_count));
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!is_primary, egui::Button::new("Primary")).clicked() {
+                            world.send_event::<ZoneEvent>(ZoneEvent::SetZoneAsPrimary(row.entity));
+                        }
+                        if ui.button("Save").clicked() {
+                            world.send_event::<ZoneEvent>(ZoneEvent::SaveZoneToFile(row.entity));
+                        }
+                        if ui
+                            .button("Export glTF")
+                            .on_hover_text("Write the zone layout to a .glb for Blender and other tools")
+                            .clicked()
+                        {
+                            state.gltf_export_result = Some(export_zone_to_gltf(world, row.entity));
+                        }
+
+                        let confirming = state.confirm_discard == Some(row.entity);
+
+                        if confirming {
+                            ui.label(RichText::new("Discard unsaved changes?").color(egui::Color32::YELLOW));
+                            if ui.button("Reload").clicked() {
+                                world.send_event::<ZoneEvent>(ZoneEvent::ReloadZoneFromDisk(row.entity));
+                                state.confirm_discard = None;
+                            }
+                            if ui.button("Unload").clicked() {
+                                world.send_event::<ZoneEvent>(ZoneEvent::UnloadZone(row.entity));
+                                state.confirm_discard = None;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                state.confirm_discard = None;
+                            }
+                        } else {
+                            if ui
+                                .add_enabled(row.file_path.is_some(), egui::Button::new("Reload"))
+                                .clicked()
+                            {
+                                match row.dirty {
+                                    true => state.confirm_discard = Some(row.entity),
+                                    false => {
+                                        world.send_event::<ZoneEvent>(ZoneEvent::ReloadZoneFromDisk(row.entity));
+                                    }
+                                };
+                            }
+                            if ui.button("Unload").clicked() {
+                                match row.dirty {
+                                    true => state.confirm_discard = Some(row.entity),
+                                    false => {
+                                        world.send_event::<ZoneEvent>(ZoneEvent::UnloadZone(row.entity));
+                                    }
+                                };
+                            }
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+        match &state.gltf_export_result {
+            Some(Ok(export)) => {
+                ui.label(
+                    RichText::new(format!(
+                        "Exported {} doodads to {:?}",
+                        export.doodad_count, export.file_path
+                    ))
+                    .color(egui::Color32::GREEN),
+                );
+                for warning in export.warnings.iter() {
+                    ui.label(RichText::new(warning).color(egui::Color32::YELLOW));
+                }
+            }
+            Some(Err(error)) => {
+                ui.label(
+                    RichText::new(format!("glTF export failed: {}", error)).color(egui::Color32::RED),
+                );
+            }
+            None => {}
+        }
+
+        if let Some(primary_zone) = primary_zone_entity.filter(|ent| world.get_entity(*ent).is_some()) {
+            ui.separator();
+            egui::CollapsingHeader::new(format!("Zone Origin: {}", primary_zone_name))
+                .id_source("zone_origin")
+                .show(ui, |ui| {
+                    zone_origin_ui(world, &mut state.origin, primary_zone, ui);
+                });
+        }
+
+        ui.separator();
+        egui::CollapsingHeader::new("Zone Instances")
+            .id_source("zone_instances")
+            .show(ui, |ui| {
+                zone_instances_ui(world, &mut state.instances, primary_zone_entity, ui);
+            });
+
+        ui.separator();
+        egui::CollapsingHeader::new("Import glTF Layout")
+            .id_source("gltf_import")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    egui::TextEdit::singleline(&mut state.gltf_import_path)
+                        .hint_text("path/to/layout.glb")
+                        .desired_width(240.0)
+                        .show(ui);
+
+                    let can_import = primary_zone_entity.is_some() && !state.gltf_import_path.trim().is_empty();
+                    if ui
+                        .add_enabled(can_import, egui::Button::new("Import Into Primary Zone"))
+                        .on_hover_text("Place a doodad for every node whose name, mesh or model matches the doodad manifest")
+                        .clicked()
+                    {
+                        state.gltf_import_result =
+                            Some(import_gltf_layout(world, state.gltf_import_path.trim()));
+                    }
+                });
+
+                match &state.gltf_import_result {
+                    Some(Ok(import)) => {
+                        ui.label(
+                            RichText::new(format!(
+                                "Placed {} doodads from {:?}",
+                                import.placed_count, import.file_path
+                            ))
+                            .color(egui::Color32::GREEN),
+                        );
+                        if !import.unmatched_nodes.is_empty() {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} nodes did not match a doodad:",
+                                    import.unmatched_nodes.len()
+                                ))
+                                .color(egui::Color32::YELLOW),
+                            );
+                            egui::ScrollArea::vertical()
+                                .id_source("gltf_import_unmatched")
+                                .max_height(120.0)
+                                .show(ui, |ui| {
+                                    for unmatched_node in import.unmatched_nodes.iter() {
+                                        ui.label(unmatched_node);
+                                    }
+                                });
+                        }
+                    }
+                    Some(Err(error)) => {
+                        ui.label(
+                            RichText::new(format!("glTF import failed: {}", error))
+                                .color(egui::Color32::RED),
+                        );
+                    }
+                    None => {}
+                }
+            });
+
+        ui.separator();
+        changed_zone_files_ui(world, ui);
+        zone_prop_validation_ui(world, ui);
+
+        let upgraded_zones: Vec<(Entity, String, u32)> = world
+            .resource::<ZoneMigrationResource>()
+            .upgraded_zones
+            .iter()
+            .map(|z| (z.zone_entity, z.file_name.clone(), z.from_version))
+            .collect();
+
+        if !upgraded_zones.is_empty() {
+            ui.separator();
+            ui.label(
+                RichText::new("Zones upgraded from an older file format:")
+                    .color(egui::Color32::YELLOW),
+            );
+
+            for (zone_entity, file_name, from_version) in upgraded_zones.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} (v{})", file_name, from_version));
+                    if ui.button("Re-save").clicked() {
+                        world.send_event::<ZoneEvent>(ZoneEvent::SaveZoneToFile(*zone_entity));
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Re-save All").clicked() {
+                    for (zone_entity, _, _) in upgraded_zones.iter() {
+                        world.send_event::<ZoneEvent>(ZoneEvent::SaveZoneToFile(*zone_entity));
+                    }
+                }
+                if ui.button("Dismiss").clicked() {
+                    world
+                        .resource_mut::<ZoneMigrationResource>()
+                        .upgraded_zones
+                        .clear();
+                }
+            });
+            ui.separator();
+        }
+
+        ui.separator();
+        egui::CollapsingHeader::new("Zone Files")
+            .default_open(true)
+            .show(ui, |ui| {
+                if let Some(result) = zone_file_browser_ui(world, &mut state.file_browser, ui) {
+                    state.zone_create_result = Some(result);
+                }
+            });
+
         if let Some(status) = &state.zone_create_result {
             match status {
                 Ok(()) => {
@@ -151,6 +520,362 @@ impl EditorWindow for ZoneWindow {
     }
 }
 
+/// Path of the file a zone is loaded from.  RON is used when both formats exist, since that
+/// is the copy being authored.  Zones that do not exist yet are new RON files.
+pub fn zone_file_path(zone_name: &str) -> String {
+    let binary_path = zone_file_path_with_format(zone_name, ZoneFileFormat::Binary);
+
+    match !Path::new(&zone_file_path_with_format(zone_name, ZoneFileFormat::Ron)).is_file()
+        && Path::new(&binary_path).is_file()
+    {
+        true => binary_path,
+        false => zone_file_path_with_format(zone_name, ZoneFileFormat::Ron),
+    }
+}
+
+pub fn zone_file_path_with_format(zone_name: &str, format: ZoneFileFormat) -> String {
+    editor_project()
+        .zones_folder_path()
+        .join(format!("{}.{}", zone_file_stem(zone_name), format.extension()))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// File name of a zone without the `.zone` / `.zone.ron` / `.zone.bin` extension.
+pub fn zone_file_stem(zone_name: &str) -> String {
+    match zone_name.ends_with("zone.ron") || zone_name.ends_with("zone.bin") || zone_name.ends_with("zone") {
+        true => {
+            let parts: Vec<&str> = zone_name.split('.').collect();
+
+            parts.first().unwrap().to_string()
+        }
+        false => zone_name.to_string(),
+    }
+}
+
+/// Every descendant of a zone, for computing its bounds.  Unlike the children query this
+/// includes unnamed entities, like the meshes inside a gltf scene.
+pub type ZoneBoundsQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static Children>,
+        Option<&'static GlobalTransform>,
+        Option<&'static Aabb>,
+    ),
+>;
+
+/// Everything build_zone_file and the prop schema check on save read.
+#[derive(SystemParam)]
+pub struct ZoneFileQueries<'w, 's> {
+    pub children_query: Query<'w, 's, &'static Children, With<Name>>,
+    pub zone_entity_query: Query<
+        'w,
+        's,
+        (
+            &'static Name,
+            &'static Transform,
+            Option<&'static CustomPropsComponent>,
+        ),
+    >,
+    pub parent_query: Query<'w, 's, &'static Parent>,
+    pub zone_metadata_query: Query<'w, 's, &'static ZoneMetadataComponent>,
+    pub zone_bounds_query: ZoneBoundsQuery<'w, 's>,
+    pub zone_instance_query: Query<'w, 's, &'static ZoneInstanceComponent>,
+    pub doodad_id_query: Query<'w, 's, &'static DoodadId>,
+    pub doodad_query: Query<'w, 's, &'static DoodadComponent>,
+}
+
+/// The named descendants of a zone that are saved as zone entities, parents before children,
+/// and the instance nodes directly in the zone.  Instanced zones are not descended into since
+/// their contents come from their own file.
+pub fn zone_file_entities(zone_entity: Entity, queries: &ZoneFileQueries) -> (Vec<Entity>, Vec<Entity>) {
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut instance_nodes: Vec<Entity> = Vec::new();
+
+    //breadth first like DescendantIter
+    let mut queue: std::collections::VecDeque<Entity> = std::collections::VecDeque::new();
+    queue.push_back(zone_entity);
+
+    while let Some(entity) = queue.pop_front() {
+        let Ok(children) = queries.children_query.get(entity) else {
+            continue;
+        };
+
+        for child in children.iter() {
+            match queries.zone_instance_query.contains(*child) {
+                true => instance_nodes.push(*child),
+                false => {
+                    entities.push(*child);
+                    queue.push_back(*child);
+                }
+            }
+        }
+    }
+
+    (entities, instance_nodes)
+}
+
+pub fn build_zone_file(zone_entity: Entity, queries: &ZoneFileQueries) -> ZoneFile {
+    let (all_children, instance_nodes) = zone_file_entities(zone_entity, queries);
+
+    let zone_instances: Vec<ZoneInstance> = instance_nodes
+        .iter()
+        .filter_map(|instance_node| {
+            let zone_instance = queries.zone_instance_query.get(*instance_node).ok()?;
+            let (_, transform, _) = queries.zone_entity_query.get(*instance_node).ok()?;
+
+            Some(ZoneInstance {
+                zone_name: zone_instance.zone_name.clone(),
+                transform: (*transform).into(),
+            })
+        })
+        .collect();
+
+    let mut metadata = queries
+        .zone_metadata_query
+        .get(zone_entity)
+        .map(|comp| comp.metadata.clone())
+        .unwrap_or_default();
+    metadata.bounds = compute_zone_bounds(zone_entity, &queries.zone_bounds_query);
+
+    let root_transform = queries
+        .zone_entity_query
+        .get(zone_entity)
+        .map(|(_, transform, _)| *transform)
+        .unwrap_or_default();
+
+    ZoneFile::new(
+        metadata,
+        root_transform,
+        all_children,
+        zone_instances,
+        &queries.zone_entity_query,
+        &queries.parent_query,
+        &queries.doodad_id_query,
+    )
+}
+
+/// Sends a PlaceDoodadEvent for every entity of a zone file.  Entities without a parent in the
+/// file go under `root_parent`, or directly under the zone.  Returns the reserved entities, in file order.
+pub(crate) fn spawn_zone_entities(
+    commands: &mut Commands,
+    spawn_doodad_event_writer: &mut EventWriter<PlaceDoodadEvent>,
+    zone_entities: Vec<ZoneEntity>,
+    zone: Entity,
+    root_parent: Option<Entity>,
+) -> Vec<Entity> {
+    //reserve every entity up front so children can be parented to doodads that are not spawned yet
+    let reserved_entities: Vec<Entity> = zone_entities
+        .iter()
+        .map(|_| commands.spawn_empty().id())
+        .collect();
+
+    //trigger spawn doodad events
+
+    for (index, zone_entity) in zone_entities.into_iter().enumerate() {
+        let parent = zone_entity
+            .get_parent_index()
+            .filter(|parent_index| *parent_index < index)
+            .map(|parent_index| reserved_entities[parent_index])
+            .or(root_parent);
+
+        spawn_doodad_event_writer.send({
+            PlaceDoodadEvent {
+                doodad_name: zone_entity.name.clone(),
+                position: zone_entity.get_position(),
+                rotation_euler: None,
+                rotation: Some(zone_entity.get_rotation()),
+                scale: Some(zone_entity.get_scale()),
+                custom_props: zone_entity.get_custom_props().clone(),
+                zone: Some(zone),
+                parent,
+                reserved_entity: Some(reserved_entities[index]),
+                id: zone_entity.get_id(),
+            }
+        });
+    }
+
+    reserved_entities
+}
+
+//world space box around every mesh in the zone, or around the doodad origins when they have no mesh
+pub(crate) fn compute_zone_bounds(zone_entity: Entity, zone_bounds_query: &ZoneBoundsQuery) -> Option<ZoneBounds> {
+    let mut bounds: Option<ZoneBounds> = None;
+    let mut extend = |point: Vec3| match bounds.as_mut() {
+        Some(bounds) => bounds.extend(point),
+        None => bounds = Some(ZoneBounds::from_point(point)),
+    };
+
+    let mut stack: Vec<Entity> = zone_bounds_query
+        .get(zone_entity)
+        .ok()
+        .and_then(|(children, _, _)| children)
+        .map(|children| children.iter().copied().collect())
+        .unwrap_or_default();
+
+    while let Some(entity) = stack.pop() {
+        let Ok((children, global_transform, aabb)) = zone_bounds_query.get(entity) else {
+            continue;
+        };
+
+        if let Some(children) = children {
+            stack.extend(children.iter().copied());
+        }
+
+        let Some(global_transform) = global_transform else {
+            continue;
+        };
+
+        match aabb {
+            Some(aabb) => {
+                let center = Vec3::from(aabb.center);
+                let half_extents = Vec3::from(aabb.half_extents);
+
+                for corner in 0..8 {
+                    let sign = Vec3::new(
+                        if corner & 1 == 0 { -1.0 } else { 1.0 },
+                        if corner & 2 == 0 { -1.0 } else { 1.0 },
+                        if corner & 4 == 0 { -1.0 } else { 1.0 },
+                    );
+                    extend(global_transform.transform_point(center + half_extents * sign));
+                }
+            }
+            None => extend(global_transform.translation()),
+        }
+    }
+
+    bounds
+}
+
+fn find_parent_zone(
+    entity: Entity,
+    parent_query: &Query<&Parent>,
+    zone_query: &Query<(), With<ZoneComponent>>,
+) -> Option<Entity> {
+    let mut current_entity = entity;
+    while let Ok(parent) = parent_query.get(current_entity) {
+        current_entity = parent.get();
+        if zone_query.contains(current_entity) {
+            return Some(current_entity);
+        }
+    }
+    None
+}
+
+fn is_in_zone_instance(
+    entity: Entity,
+    parent_query: &Query<&Parent>,
+    zone_instance_query: &Query<(), With<ZoneInstanceComponent>>,
+) -> bool {
+    let mut current_entity = entity;
+    while let Ok(parent) = parent_query.get(current_entity) {
+        current_entity = parent.get();
+        if zone_instance_query.contains(current_entity) {
+            return true;
+        }
+    }
+    false
+}
+
+pub fn mark_dirty_zones(
+    mut commands: Commands,
+
+    changed_doodad_query: Query<
+        (Entity, Ref<DoodadComponent>, Has<SpawnedFromZoneFile>),
+        Or<(
+            Changed<Transform>,
+            Changed<CustomPropsComponent>,
+            Changed<Parent>,
+            Changed<Name>,
+            Added<DoodadComponent>,
+        )>,
+    >,
+    mut removed_doodads: RemovedComponents<DoodadComponent>,
+
+    parent_query: Query<&Parent>,
+    zone_query: Query<(), With<ZoneComponent>>,
+    reloaded_zone_query: Query<Entity, With<ReloadedZone>>,
+    zone_instance_query: Query<(), With<ZoneInstanceComponent>>,
+    changed_zone_instance_query: Query<
+        (Entity, Ref<Transform>),
+        (With<ZoneInstanceComponent>, Changed<Transform>),
+    >,
+    changed_zone_root_query: Query<
+        (Entity, Ref<Transform>, Ref<ZoneMetadataComponent>),
+        (
+            With<ZoneComponent>,
+            Or<(Changed<Transform>, Changed<ZoneMetadataComponent>)>,
+            Without<ReloadedZone>,
+        ),
+    >,
+
+    //which zone each doodad was in, so removals and moves can dirty the old zone
+    mut doodad_zones: Local<HashMap<Entity, Entity>>,
+) {
+    let mut dirty_zones: Vec<Entity> = Vec::new();
+
+    for (entity, doodad_comp, spawned_from_zone_file) in changed_doodad_query.iter() {
+        //instanced doodads are never saved with the zone they are shown in
+        if is_in_zone_instance(entity, &parent_query, &zone_instance_query) {
+            doodad_zones.remove(&entity);
+            continue;
+        }
+
+        let zone = find_parent_zone(entity, &parent_query, &zone_query);
+
+        let previous_zone = match zone {
+            Some(zone) => doodad_zones.insert(entity, zone),
+            None => doodad_zones.remove(&entity),
+        };
+
+        //everything set while spawning a loaded doodad matches the file
+        if doodad_comp.is_added() && spawned_from_zone_file {
+            commands.entity(entity).remove::<SpawnedFromZoneFile>();
+            continue;
+        }
+
+        dirty_zones.extend(zone);
+        dirty_zones.extend(previous_zone.filter(|prev| Some(*prev) != zone));
+    }
+
+    //the root transform and metadata set while loading or creating the zone match the file
+    for (zone, transform, metadata) in changed_zone_root_query.iter() {
+        let transform_edited = transform.is_changed() && !transform.is_added();
+        let metadata_edited = metadata.is_changed() && !metadata.is_added();
+
+        if transform_edited || metadata_edited {
+            dirty_zones.push(zone);
+        }
+    }
+
+    //moving an instance node changes the instance offset saved in its zone
+    for (entity, transform) in changed_zone_instance_query.iter() {
+        if transform.is_added() || is_in_zone_instance(entity, &parent_query, &zone_instance_query) {
+            continue;
+        }
+        dirty_zones.extend(find_parent_zone(entity, &parent_query, &zone_query));
+    }
+
+    for entity in removed_doodads.read() {
+        if let Some(zone) = doodad_zones.remove(&entity) {
+            if !reloaded_zone_query.contains(zone) {
+                dirty_zones.push(zone);
+            }
+        }
+    }
+
+    for zone in reloaded_zone_query.iter() {
+        commands.entity(zone).remove::<ReloadedZone>();
+    }
+
+    for zone in dirty_zones {
+        if zone_query.contains(zone) {
+            commands.entity(zone).insert(ZoneDirty);
+        }
+    }
+}
+
 fn create_zone(
     //  world: &mut World,
     world: &mut World,
@@ -165,6 +890,15 @@ fn load_zone(
     world: &mut World,
     name: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !Path::new(&zone_file_path(name)).is_file() {
+        return Err(format!(
+            "No zone file named {:?} in {:?}",
+            zone_file_stem(name),
+            editor_project().zones_folder_path()
+        )
+        .into());
+    }
+
     world.send_event::<ZoneEvent>(ZoneEvent::LoadZoneFile(name.into()));
 
     Ok(())
@@ -187,15 +921,24 @@ fn load_all_zones(
 }
 
 
-fn get_all_zone_file_names() -> Vec<String> {
-    let zones_dir = Path::new("assets/zones");
+/// Sorted names of every `*.zone.ron` and `*.zone.bin` file in the zones folder, without the
+/// `.ron` / `.bin` extension.  A zone saved in both formats is listed once.
+pub(crate) fn get_all_zone_file_names() -> Vec<String> {
+    let zones_dir = editor_project().zones_folder_path();
 
-    let file_names = match fs::read_dir(zones_dir) {
+    let mut file_names: Vec<String> = match fs::read_dir(&zones_dir) {
         Ok(entries) => entries
             .filter_map(|entry| {
                 entry.ok().and_then(|e| {
                     let path = e.path();
-                    if path.is_file() && path.extension().map_or(false, |ext| ext == "ron") {
+                    let is_zone_file = path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(false, |name| {
+                            name.ends_with(".zone.ron") || name.ends_with(".zone.bin")
+                        });
+
+                    if path.is_file() && is_zone_file {
                         path.file_stem().and_then(|stem| stem.to_str().map(|s| s.to_string()))
                     } else {
                         None
@@ -209,21 +952,45 @@ fn get_all_zone_file_names() -> Vec<String> {
         }
     };
 
+    file_names.sort();
+    file_names.dedup();
+
     file_names
 }
 
+//entities of a zone that is despawned outside of the history
+fn zone_and_descendants(zone_entity: Entity, zone_file_queries: &ZoneFileQueries) -> Vec<Entity> {
+    std::iter::once(zone_entity)
+        .chain(zone_file_queries.children_query.iter_descendants(zone_entity))
+        .collect()
+}
+
 pub fn handle_zone_events(
     mut commands: Commands,
     mut evt_reader: EventReader<ZoneEvent>,
 
     mut zone_resource: ResMut<ZoneResource>,
+    mut zone_migration_resource: ResMut<ZoneMigrationResource>,
+    mut zone_file_watcher: ResMut<ZoneFileWatcher>,
+    mut zone_prop_validation_resource: ResMut<ZonePropValidationResource>,
+    mut editor: ResMut<Editor>,
+    mut editor_history: ResMut<EditorHistory>,
 
-    children_query: Query<&Children, With<Name>>,
+    mut zone_instance_resource: ResMut<ZoneInstanceResource>,
 
-    zone_entity_query: Query<(&Name, &Transform, Option<&CustomPropsComponent>)>,
+    zone_file_queries: ZoneFileQueries,
+    dirty_zone_query: Query<Entity, (With<ZoneComponent>, With<ZoneDirty>)>,
+    loaded_zone_query: Query<(Entity, &Name, Option<&ZoneFilePath>), With<ZoneComponent>>,
+    zone_instance_node_query: Query<(Entity, &ZoneInstanceComponent)>,
+    zone_query: Query<(), With<ZoneComponent>>,
 
     mut spawn_doodad_event_writer: EventWriter<PlaceDoodadEvent>,
+    mut zone_save_failed_writer: EventWriter<ZoneSaveFailed>,
 ) {
+    let mut loaded_this_frame: HashSet<String> = HashSet::new();
+    //zones whose instances have to be respawned, once every event is handled
+    let mut instances_to_refresh: Vec<String> = Vec::new();
+
     for evt in evt_reader.read() {
         match evt {
             ZoneEvent::CreateNewZone(name) => {
@@ -231,6 +998,9 @@ pub fn handle_zone_events(
                     .spawn(SpatialBundle::default())
                     .insert(ZoneComponent {})
                     .insert(Name::new(name.to_string()))
+                    .insert(ZoneMetadataComponent::default())
+                    //not on disk yet
+                    .insert(ZoneDirty)
                     .id();
 
                 zone_resource.primary_zone = Some(created_zone);
@@ -242,110 +1012,390 @@ pub fn handle_zone_events(
             ZoneEvent::ResetPrimaryZone => {
                 zone_resource.primary_zone = None;
             }
-            ZoneEvent::SaveZoneToFile(ent) => {
+            ZoneEvent::SaveZoneToFile(_) | ZoneEvent::SaveAllDirtyZones => {
+                let zones_to_save: Vec<Entity> = match evt {
+                    ZoneEvent::SaveZoneToFile(ent) => vec![ent.clone()],
+                    _ => dirty_zone_query.iter().collect(),
+                };
+
+                for ent in zones_to_save.iter() {
                 //this is kind of wacky but we are using this as a poor mans name query
-                let Some((zone_name_comp, _, _)) = zone_entity_query.get(ent.clone()).ok() else {
-                    return;
+                let Some((zone_name_comp, _, _)) = zone_file_queries.zone_entity_query.get(ent.clone()).ok() else {
+                    continue;
                 };
 
-                let zone_name: &str = zone_name_comp.as_str();
+                let fixed_zone_name = zone_file_stem(zone_name_comp.as_str());
 
+                let prop_violations = zone_prop_violations(*ent, &zone_file_queries);
+                match prop_violations.is_empty() {
+                    true => zone_prop_validation_resource.clear(*ent),
+                    false => {
+                        let refused =
+                            editor_project().invalid_props_on_save == InvalidPropsOnSave::Refuse;
 
-                let fixed_zone_name = match zone_name.ends_with( "zone.ron" ) || zone_name.ends_with( "zone" ){
+                        for violation in prop_violations.iter() {
+                            eprintln!("zone {} custom props: {}", fixed_zone_name, violation);
+                        }
 
-                    true => {
+                        zone_prop_validation_resource.record(InvalidZoneProps {
+                            zone_entity: *ent,
+                            zone_name: fixed_zone_name.clone(),
+                            refused,
+                            messages: prop_violations,
+                        });
+
+                        if refused {
+                            eprintln!("not saving zone {}, fix its custom props first", fixed_zone_name);
+                            zone_save_failed_writer.send(ZoneSaveFailed {
+                                zone_entity: *ent,
+                                zone_name: fixed_zone_name.clone(),
+                                reason: "invalid custom props".into(),
+                            });
+                            continue;
+                        }
+                    }
+                }
 
-                          let   parts: Vec<&str> = zone_name.split('.').collect();
-                         
-                            parts.first().unwrap() .to_string()  
-                           
+                let zone_file = build_zone_file(ent.clone(), &zone_file_queries);
 
-                      }, 
-                    false => zone_name.to_string()
+                //zones stay in the format they were loaded from, new zones are saved as RON
+                let zone_file_format = loaded_zone_query
+                    .get(*ent)
+                    .ok()
+                    .and_then(|(_, _, file_path)| file_path)
+                    .map(|file_path| ZoneFileFormat::from_path(&file_path.0))
+                    .unwrap_or_else(|| ZoneFileFormat::from_path(zone_file_path(&fixed_zone_name)));
 
-                };
-                
+                let zone_file_name = zone_file_path_with_format(&fixed_zone_name, zone_file_format);
 
-                let mut all_children: Vec<Entity> = Vec::new();
+                let mut file_saved = write_zone_file(&zone_file_name, &zone_file);
 
-                for child in DescendantIter::new(&children_query, ent.clone()) {
-                    all_children.push(child);
+                //a binary copy next to the RON file is rewritten so games never load a stale zone
+                let binary_copy_path = zone_file_path_with_format(&fixed_zone_name, ZoneFileFormat::Binary);
+                if file_saved.is_ok()
+                    && zone_file_format == ZoneFileFormat::Ron
+                    && Path::new(&binary_copy_path).is_file()
+                {
+                    file_saved = write_zone_file(&binary_copy_path, &zone_file);
+                    zone_file_watcher.record_file(&binary_copy_path);
                 }
 
-                let zone_file = ZoneFile::new(all_children, &zone_entity_query);
+                println!("exported zone ! {:?}", file_saved);
 
-                let zone_file_name = format!("assets/zones/{}.zone.ron", fixed_zone_name);
+                if file_saved.is_ok() {
+                    zone_migration_resource
+                        .upgraded_zones
+                        .retain(|z| z.zone_entity != *ent);
 
-                let ron = ron::ser::to_string(&zone_file).unwrap();
-                let file_saved = std::fs::write(zone_file_name, ron);
+                    //our own write is not an outside change, and it overwrites any that was pending
+                    zone_file_watcher.record_file(&zone_file_name);
+                    zone_file_watcher.dismiss(*ent);
 
-                println!("exported zone ! {:?}", file_saved);
-            }
+                    instances_to_refresh.push(fixed_zone_name.clone());
 
-            ZoneEvent::LoadZoneFile(zone_name) => {
+                    commands
+                        .entity(ent.clone())
+                        .remove::<ZoneDirty>()
+                        .insert(ZoneFilePath(zone_file_name.clone()));
+                }
 
-                let fixed_zone_name = match zone_name.ends_with( "zone.ron" ) || zone_name.ends_with( "zone" ){
+                if let Err(e) = file_saved {
+                    zone_save_failed_writer.send(ZoneSaveFailed {
+                        zone_entity: *ent,
+                        zone_name: fixed_zone_name.clone(),
+                        reason: e.to_string(),
+                    });
+                }
+                }
+            }
+
+            ZoneEvent::UnloadZone(ent) => {
+                if !loaded_zone_query.contains(*ent) {
+                    continue;
+                }
 
-                    true => {
+                editor_history.forget_entities(&zone_and_descendants(*ent, &zone_file_queries));
+                commands.entity(*ent).despawn_recursive();
+                zone_prop_validation_resource.clear(*ent);
 
-                          let   parts: Vec<&str> = zone_name.split('.').collect();
-                         
-                            parts.first().unwrap() .to_string()  
-                           
+                if zone_resource.primary_zone == Some(*ent) {
+                    zone_resource.primary_zone = None;
+                }
+                zone_migration_resource
+                    .upgraded_zones
+                    .retain(|z| z.zone_entity != *ent);
 
-                      }, 
-                    false => zone_name.to_string()
+                println!("unloaded zone {:?}", ent);
+            }
 
+            ZoneEvent::AddZoneInstance { zone, zone_name } => {
+                let Ok((_, host_name, _)) = loaded_zone_query.get(*zone) else {
+                    continue;
                 };
 
+                let mut chain = vec![zone_file_stem(host_name.as_str())];
+
+                let instance_node = spawn_zone_instance(
+                    &mut commands,
+                    &mut spawn_doodad_event_writer,
+                    &ZoneInstance {
+                        zone_name: zone_name.clone(),
+                        transform: Transform::IDENTITY.into(),
+                    },
+                    *zone,
+                    *zone,
+                    &mut chain,
+                    &mut zone_instance_resource.errors,
+                );
+
+                if instance_node.is_some() {
+                    commands.entity(*zone).insert(ZoneDirty);
+                }
+            }
 
-                let file_name = format!("assets/zones/{}.zone.ron", fixed_zone_name);
- 
-                 let path = Path::new(&file_name);
+            ZoneEvent::RefreshZoneInstances(zone_name) => {
+                instances_to_refresh.push(zone_file_stem(zone_name));
+            }
 
-                // Read the file into a string
-                let Ok(file_content) = std::fs::read_to_string(path) else {
-                    println!("Could not find file {:?}", file_name);
-                    return;
+            ZoneEvent::LoadZoneFile(_)
+            | ZoneEvent::RestoreZoneFromRecovery { .. }
+            | ZoneEvent::ReloadZoneFromDisk(_) => {
+
+                //zones that the loaded file takes the place of
+                let mut replaced_zones: Vec<Entity> = Vec::new();
+                //zone that keeps its entity and only has its doodads respawned
+                let mut reloading_zone: Option<Entity> = None;
+
+                let (zone_name, file_name, restoring) = match evt {
+                    ZoneEvent::RestoreZoneFromRecovery { zone_name, recovery_file } => {
+                        //the recovered copy replaces the version loaded from the zones folder
+                        replaced_zones.extend(
+                            loaded_zone_query
+                                .iter()
+                                .filter(|(_, name, _)| name.as_str() == zone_name.as_str())
+                                .map(|(ent, _, _)| ent),
+                        );
+                        (zone_name.clone(), recovery_file.clone(), true)
+                    }
+                    ZoneEvent::ReloadZoneFromDisk(ent) => {
+                        let Ok((_, name, file_path)) = loaded_zone_query.get(*ent) else {
+                            continue;
+                        };
+                        reloading_zone = Some(*ent);
+                        (
+                            name.as_str().to_string(),
+                            file_path
+                                .map(|p| p.0.clone())
+                                .unwrap_or_else(|| zone_file_path(name.as_str())),
+                            false,
+                        )
+                    }
+                    ZoneEvent::LoadZoneFile(zone_name) => {
+                        let stem = zone_file_stem(zone_name);
+
+                        let already_loaded = loaded_zone_query
+                            .iter()
+                            .any(|(_, name, _)| zone_file_stem(name.as_str()) == stem);
+
+                        //also catches the same zone requested twice in one frame, before the first is spawned
+                        if already_loaded || !loaded_this_frame.insert(stem) {
+                            println!("zone {:?} is already loaded", zone_name);
+                            continue;
+                        }
+
+                        (zone_name.clone(), zone_file_path(zone_name), false)
+                    }
+                    _ => continue,
                 };
 
+                 let path = Path::new(&file_name);
+
+                if !path.is_file() {
+                    println!("Could not find file {:?}", file_name);
+                    continue;
+                }
 
-                let zone_file = match ron::from_str::<ZoneFile>(&file_content) {
+                //RON or binary, depending on the file extension
+                let (zone_file, upgraded_from) = match read_zone_file(path) {
 
                     Ok(f) => f ,
 
                     Err(e) =>  {
                         eprintln!("Could not parse file {:?} {:?}", file_name, e); 
-                        return;
+                        continue;
                     }
                 };
                
-                //spawnn the zone entity and set it as primary
-
-                let created_zone = commands
-                    .spawn(SpatialBundle::default())
-                    .insert(ZoneComponent {})
-                    .insert(Name::new(zone_name.to_string()))
-                    
-                    .id();
+                for replaced_zone in replaced_zones.iter().chain(reloading_zone.iter()) {
+                    zone_migration_resource
+                        .upgraded_zones
+                        .retain(|z| z.zone_entity != *replaced_zone);
+                    zone_file_watcher.dismiss(*replaced_zone);
+                    //undo must not bring back doodads of the discarded version
+                    editor_history
+                        .forget_entities(&zone_and_descendants(*replaced_zone, &zone_file_queries));
+                }
 
-                zone_resource.primary_zone = Some(created_zone);
+                for replaced_zone in replaced_zones.iter() {
+                    commands.entity(*replaced_zone).despawn_recursive();
+                }
 
-                //trigger spawn doodad events
-
-                for zone_entity in zone_file.entities {
-                    spawn_doodad_event_writer.send({
-                        PlaceDoodadEvent {
-                            doodad_name: zone_entity.name.clone(),
-                            position: zone_entity.get_position(),
-                            rotation_euler: Some(zone_entity.get_rotation_euler()),
-                            scale: Some(zone_entity.get_scale()),
-                            custom_props: zone_entity.get_custom_props().clone(),
-                            zone:Some(created_zone)
+                //ids of the selected doodads of a reloaded zone
+                let mut reselect_ids: Vec<DoodadId> = Vec::new();
+
+                let created_zone = match reloading_zone {
+                    Some(zone_entity) => {
+                        let selected = &mut editor
+                            .window_state_mut::<HierarchyWindow>()
+                            .unwrap()
+                            .selected;
+
+                        let (zone_entities, _) = zone_file_entities(zone_entity, &zone_file_queries);
+                        for child in zone_entities {
+                            if selected.contains(child) {
+                                selected.remove(child);
+                                reselect_ids.extend(zone_file_queries.doodad_id_query.get(child).ok());
+                            }
                         }
+
+                        commands
+                            .entity(zone_entity)
+                            .despawn_descendants()
+                            .remove::<ZoneDirty>()
+                            .insert(ReloadedZone)
+                            .insert(zone_file.get_root_transform())
+                            .insert(ZoneMetadataComponent {
+                                metadata: zone_file.metadata.clone(),
+                            });
+
+                        zone_entity
+                    }
+                    None => {
+                        //spawnn the zone entity and set it as primary
+
+                        let created_zone = commands
+                            .spawn(SpatialBundle {
+                                transform: zone_file.get_root_transform(),
+                                ..default()
+                            })
+                            .insert(ZoneComponent {})
+                            .insert(Name::new(zone_name.to_string()))
+                            .insert(ZoneMetadataComponent {
+                                metadata: zone_file.metadata.clone(),
+                            })
+                            //restored zones still belong to their file in the zones folder
+                            .insert(ZoneFilePath(match restoring {
+                                true => zone_file_path(&zone_name),
+                                false => file_name.clone(),
+                            }))
+                            .id();
+
+                        zone_resource.primary_zone = Some(created_zone);
+
+                        created_zone
+                    }
+                };
+
+                //recovered edits were never written to the zone file
+                if restoring {
+                    commands.entity(created_zone).insert(ZoneDirty);
+                }
+
+                if let Some(from_version) = upgraded_from {
+                    println!(
+                        "upgraded zone file {:?} from format v{} to v{}",
+                        file_name, from_version, zone_file.format_version
+                    );
+
+                    zone_migration_resource.upgraded_zones.push(UpgradedZone {
+                        zone_entity: created_zone,
+                        file_name: file_name.clone(),
+                        from_version,
                     });
+
+                    //the upgrade, including any ids it handed out, is only kept once the zone is saved
+                    commands.entity(created_zone).insert(ZoneDirty);
+                }
+
+                let zone_instances = zone_file.zone_instances.clone();
+
+                let file_ids: Vec<Option<DoodadId>> =
+                    zone_file.entities.iter().map(|e| e.get_id()).collect();
+
+                let reserved_entities = spawn_zone_entities(
+                    &mut commands,
+                    &mut spawn_doodad_event_writer,
+                    zone_file.entities,
+                    created_zone,
+                    None,
+                );
+
+                //keep the selection on doodads that are still in the file
+                if !reselect_ids.is_empty() {
+                    let selected = &mut editor
+                        .window_state_mut::<HierarchyWindow>()
+                        .unwrap()
+                        .selected;
+
+                    for (file_id, reserved_entity) in file_ids.iter().zip(reserved_entities.iter()) {
+                        if file_id.map_or(false, |id| reselect_ids.contains(&id)) {
+                            selected.select_maybe_add(*reserved_entity, true);
+                        }
+                    }
+                }
+
+                let mut chain = vec![zone_file_stem(&zone_name)];
+                for zone_instance in zone_instances.iter() {
+                    spawn_zone_instance(
+                        &mut commands,
+                        &mut spawn_doodad_event_writer,
+                        zone_instance,
+                        created_zone,
+                        created_zone,
+                        &mut chain,
+                        &mut zone_instance_resource.errors,
+                    );
                 }
             }
         }
     }
+
+    instances_to_refresh.sort();
+    instances_to_refresh.dedup();
+
+    for (instance_node, zone_instance) in zone_instance_node_query.iter() {
+        if !instances_to_refresh.contains(&zone_instance.zone_name) {
+            continue;
+        }
+
+        let Some(host_zone) = find_parent_zone(instance_node, &zone_file_queries.parent_query, &zone_query) else {
+            continue;
+        };
+        let Ok((_, host_name, _)) = loaded_zone_query.get(host_zone) else {
+            continue;
+        };
+
+        //instances of instances are respawned along with the outermost one
+        let mut chain = instance_chain(
+            instance_node,
+            host_name.as_str(),
+            &zone_file_queries.parent_query,
+            &zone_file_queries.zone_instance_query,
+        );
+        if chain[1..].iter().any(|name| instances_to_refresh.contains(name)) {
+            continue;
+        }
+
+        commands.entity(instance_node).despawn_descendants();
+
+        spawn_zone_instance_contents(
+            &mut commands,
+            &mut spawn_doodad_event_writer,
+            instance_node,
+            &zone_instance.zone_name,
+            host_zone,
+            &mut chain,
+            &mut zone_instance_resource.errors,
+        );
+    }
 }
@@ -0,0 +1,377 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_inspector_egui::egui::{self, RichText, ScrollArea};
+
+use crate::project::editor_project;
+
+use super::{
+    get_all_zone_file_names, zone_file_path, zone_file_path_with_format, zone_file_stem,
+    ZoneComponent, ZoneDirty, ZoneEvent, ZoneFilePath,
+};
+use super::zone_file::remap_custom_prop_doodad_references;
+use super::zone_file_format::{convert_zone_file, read_zone_file, write_zone_file, ZoneFileFormat};
+use super::zone_file_watcher::ZoneFileWatcher;
+use crate::doodads::doodad_id::DoodadId;
+
+type ZoneFileResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Default)]
+pub struct ZoneFileBrowserState {
+    search: String,
+    //read from disk lazily and after every file operation
+    zone_files: Option<Vec<String>>,
+    pending_action: Option<PendingZoneFileAction>,
+    //file list generation of the zone file watcher when zone_files was read
+    watcher_generation: u64,
+}
+
+struct PendingZoneFileAction {
+    zone_name: String,
+    kind: ZoneFileActionKind,
+    new_name: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ZoneFileActionKind {
+    Rename,
+    Duplicate,
+    Delete,
+    Convert,
+}
+
+/// Lists the zone files on disk. Returns the result of a file action when one was run this frame.
+pub(crate) fn zone_file_browser_ui(
+    world: &mut World,
+    state: &mut ZoneFileBrowserState,
+    ui: &mut egui::Ui,
+) -> Option<ZoneFileResult> {
+    let mut result = None;
+
+    //files added or removed outside of the editor
+    let watcher_generation = world.resource::<ZoneFileWatcher>().file_list_generation;
+    if watcher_generation != state.watcher_generation {
+        state.watcher_generation = watcher_generation;
+        state.zone_files = None;
+    }
+
+    let zone_files = state
+        .zone_files
+        .get_or_insert_with(|| {
+            get_all_zone_file_names()
+                .iter()
+                .map(|file_name| zone_file_stem(file_name))
+                .collect()
+        })
+        .clone();
+
+    let mut loaded_zone_query = world.query_filtered::<&Name, With<ZoneComponent>>();
+    let loaded_zone_stems: Vec<String> = loaded_zone_query
+        .iter(world)
+        .map(|name| zone_file_stem(name.as_str()))
+        .collect();
+
+    ui.horizontal(|ui| {
+        egui::TextEdit::singleline(&mut state.search)
+            .hint_text("Search zone files")
+            .desired_width(160.0)
+            .show(ui);
+
+        if ui.button("Refresh").clicked() {
+            state.zone_files = None;
+        }
+    });
+
+    let search = state.search.to_lowercase();
+
+    ScrollArea::vertical()
+        .id_source("zone_file_browser")
+        .max_height(240.0)
+        .show(ui, |ui| {
+            for zone_name in zone_files
+                .iter()
+                .filter(|name| search.is_empty() || name.to_lowercase().contains(&search))
+            {
+                let is_loaded = loaded_zone_stems.contains(zone_name);
+                let format = ZoneFileFormat::from_path(zone_file_path(zone_name));
+
+                ui.horizontal(|ui| {
+                    match is_loaded {
+                        true => ui.label(RichText::new(zone_name).strong()),
+                        false => ui.label(zone_name),
+                    };
+                    match existing_zone_file_paths(zone_name).len() > 1 {
+                        true => ui.label(RichText::new("RON + Binary").weak()),
+                        false => ui.label(RichText::new(format.label()).weak()),
+                    };
+
+                    if ui
+                        .add_enabled(!is_loaded, egui::Button::new("Load"))
+                        .on_disabled_hover_text("Already loaded")
+                        .clicked()
+                    {
+                        world.send_event::<ZoneEvent>(ZoneEvent::LoadZoneFile(zone_name.clone()));
+                    }
+
+                    for (label, kind) in [
+                        ("Rename", ZoneFileActionKind::Rename),
+                        ("Duplicate", ZoneFileActionKind::Duplicate),
+                        ("Delete", ZoneFileActionKind::Delete),
+                        (
+                            match format {
+                                ZoneFileFormat::Ron => "Binary Copy",
+                                ZoneFileFormat::Binary => "To RON",
+                            },
+                            ZoneFileActionKind::Convert,
+                        ),
+                    ] {
+                        if ui.button(label).clicked() {
+                            state.pending_action = Some(PendingZoneFileAction {
+                                zone_name: zone_name.clone(),
+                                kind,
+                                new_name: match kind {
+                                    ZoneFileActionKind::Duplicate => format!("{}_copy", zone_name),
+                                    _ => zone_name.clone(),
+                                },
+                            });
+                        }
+                    }
+                });
+
+                let Some(action) = state
+                    .pending_action
+                    .as_mut()
+                    .filter(|action| &action.zone_name == zone_name)
+                else {
+                    continue;
+                };
+
+                let mut finished = false;
+
+                ui.indent(zone_name, |ui| {
+                    ui.horizontal(|ui| {
+                        let confirm_label = match action.kind {
+                            ZoneFileActionKind::Rename => {
+                                ui.label("New name:");
+                                ui.text_edit_singleline(&mut action.new_name);
+                                "Rename"
+                            }
+                            ZoneFileActionKind::Duplicate => {
+                                ui.label("Copy name:");
+                                ui.text_edit_singleline(&mut action.new_name);
+                                "Duplicate"
+                            }
+                            ZoneFileActionKind::Delete => {
+                                ui.label(
+                                    RichText::new(format!(
+                                        "Delete {} from disk?",
+                                        existing_zone_file_paths(zone_name).join(" and ")
+                                    ))
+                                    .color(egui::Color32::YELLOW),
+                                );
+                                "Delete"
+                            }
+                            ZoneFileActionKind::Convert => {
+                                let question = match format {
+                                    ZoneFileFormat::Ron => {
+                                        "Write a binary copy? The RON file stays the source and saves update both"
+                                            .to_string()
+                                    }
+                                    ZoneFileFormat::Binary => format!(
+                                        "Replace the {} file with a {} file?",
+                                        format.label(),
+                                        format.other().label()
+                                    ),
+                                };
+                                ui.label(RichText::new(question).color(egui::Color32::YELLOW));
+                                match format {
+                                    ZoneFileFormat::Ron => "Write",
+                                    ZoneFileFormat::Binary => "Convert",
+                                }
+                            }
+                        };
+
+                        if ui.button(confirm_label).clicked() {
+                            result = Some(match action.kind {
+                                ZoneFileActionKind::Rename => {
+                                    rename_zone_file(world, zone_name, &action.new_name)
+                                }
+                                ZoneFileActionKind::Duplicate => {
+                                    duplicate_zone_file(zone_name, &action.new_name)
+                                }
+                                ZoneFileActionKind::Delete => delete_zone_file(world, zone_name),
+                                ZoneFileActionKind::Convert => {
+                                    convert_zone_file_in_place(world, zone_name)
+                                }
+                            });
+                            finished = true;
+                        }
+
+                        if ui.button("Cancel").clicked() {
+                            finished = true;
+                        }
+                    });
+                });
+
+                if finished {
+                    state.pending_action = None;
+                    state.zone_files = None;
+                }
+            }
+
+            if zone_files.is_empty() {
+                ui.label(format!(
+                    "No zone files in {:?}",
+                    editor_project().zones_folder_path()
+                ));
+            }
+        });
+
+    result
+}
+
+//a zone can be on disk in both formats, for example after exporting binary copies next to the RON files
+fn existing_zone_file_paths(zone_name: &str) -> Vec<String> {
+    [ZoneFileFormat::Ron, ZoneFileFormat::Binary]
+        .into_iter()
+        .map(|format| zone_file_path_with_format(zone_name, format))
+        .filter(|path| Path::new(path).is_file())
+        .collect()
+}
+
+fn validate_new_zone_name(new_name: &str) -> ZoneFileResult {
+    let new_name = zone_file_stem(new_name.trim());
+
+    if new_name.is_empty() {
+        return Err("Zone name cannot be empty".into());
+    }
+    if new_name.contains(|c: char| c == '/' || c == '\\' || c == '.') {
+        return Err(format!("Zone name {:?} cannot contain '/', '\\' or '.'", new_name).into());
+    }
+    if Path::new(&zone_file_path(&new_name)).exists() {
+        return Err(format!("A zone file named {:?} already exists", new_name).into());
+    }
+
+    Ok(())
+}
+
+fn rename_zone_file(world: &mut World, zone_name: &str, new_name: &str) -> ZoneFileResult {
+    validate_new_zone_name(new_name)?;
+
+    let new_name = zone_file_stem(new_name.trim());
+    let old_path = zone_file_path(zone_name);
+    let new_path = zone_file_path_with_format(&new_name, ZoneFileFormat::from_path(&old_path));
+
+    for path in existing_zone_file_paths(zone_name) {
+        fs::rename(
+            &path,
+            zone_file_path_with_format(&new_name, ZoneFileFormat::from_path(&path)),
+        )?;
+    }
+
+    //a loaded copy of the zone follows the file, so saving it writes to the new name
+    let mut loaded_zone_query =
+        world.query_filtered::<(&mut Name, Option<&mut ZoneFilePath>), With<ZoneComponent>>();
+    for (mut name, file_path) in loaded_zone_query.iter_mut(world) {
+        if zone_file_stem(name.as_str()) != zone_file_stem(zone_name) {
+            continue;
+        }
+        name.set(new_name.clone());
+        if let Some(mut file_path) = file_path {
+            file_path.0 = new_path.clone();
+        }
+    }
+
+    println!("renamed zone file {:?} to {:?}", old_path, new_path);
+
+    Ok(())
+}
+
+fn duplicate_zone_file(zone_name: &str, new_name: &str) -> ZoneFileResult {
+    validate_new_zone_name(new_name)?;
+
+    let path = zone_file_path(zone_name);
+    let new_path = zone_file_path_with_format(new_name.trim(), ZoneFileFormat::from_path(&path));
+
+    //the copy is a clone of every doodad, so they all get new ids
+    let (mut zone_file, _) = read_zone_file(&path)?;
+    let mut new_ids: HashMap<DoodadId, DoodadId> = HashMap::new();
+    for zone_entity in zone_file.entities.iter_mut() {
+        let new_id = DoodadId::new_random();
+        if let Some(old_id) = zone_entity.id {
+            new_ids.insert(old_id, new_id);
+        }
+        zone_entity.id = Some(new_id);
+    }
+
+    //links between the copied doodads follow them to the copies
+    for zone_entity in zone_file.entities.iter_mut() {
+        if let Some(props) = zone_entity.custom_props.as_mut() {
+            remap_custom_prop_doodad_references(props, &new_ids);
+        }
+    }
+    write_zone_file(&new_path, &zone_file)?;
+
+    println!("duplicated zone file {:?} to {:?}", zone_name, new_path);
+
+    Ok(())
+}
+
+fn delete_zone_file(world: &mut World, zone_name: &str) -> ZoneFileResult {
+    let paths = existing_zone_file_paths(zone_name);
+    for path in paths.iter() {
+        fs::remove_file(path)?;
+    }
+
+    //a loaded copy no longer has a file behind it
+    let mut loaded_zone_query = world.query_filtered::<(Entity, &Name), With<ZoneComponent>>();
+    let orphaned_zones: Vec<Entity> = loaded_zone_query
+        .iter(world)
+        .filter(|(_, name)| zone_file_stem(name.as_str()) == zone_file_stem(zone_name))
+        .map(|(ent, _)| ent)
+        .collect();
+
+    for zone_entity in orphaned_zones {
+        world
+            .entity_mut(zone_entity)
+            .remove::<ZoneFilePath>()
+            .insert(ZoneDirty);
+    }
+
+    println!("deleted zone files {:?}", paths);
+
+    Ok(())
+}
+
+//binary files are written for games and cannot be migrated, so a RON source is kept next to its
+//binary copy.  Converting a binary zone to RON replaces the binary file, a loaded copy saves as RON
+fn convert_zone_file_in_place(world: &mut World, zone_name: &str) -> ZoneFileResult {
+    let old_path = zone_file_path(zone_name);
+    let old_format = ZoneFileFormat::from_path(&old_path);
+    let new_path = zone_file_path_with_format(zone_name, old_format.other());
+
+    convert_zone_file(&old_path, &new_path)?;
+
+    world.resource_mut::<ZoneFileWatcher>().record_file(&new_path);
+
+    if old_format == ZoneFileFormat::Ron {
+        println!("wrote binary copy {:?} of zone file {:?}", new_path, old_path);
+        return Ok(());
+    }
+
+    fs::remove_file(&old_path)?;
+
+    let mut loaded_zone_query =
+        world.query_filtered::<(&Name, &mut ZoneFilePath), With<ZoneComponent>>();
+    for (name, mut file_path) in loaded_zone_query.iter_mut(world) {
+        if zone_file_stem(name.as_str()) == zone_file_stem(zone_name) {
+            file_path.0 = new_path.clone();
+        }
+    }
+
+    println!("converted zone file {:?} to {:?}", old_path, new_path);
+
+    Ok(())
+}
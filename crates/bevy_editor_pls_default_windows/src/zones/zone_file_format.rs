@@ -0,0 +1 @@
+pub use bevy_zone_runtime::zone_file_format::*;
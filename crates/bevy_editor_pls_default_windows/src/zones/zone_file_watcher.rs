@@ -0,0 +1,210 @@
+use std::fs;
+use std::time::SystemTime;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_inspector_egui::egui::{self, RichText};
+
+use super::zone_instance::ZoneInstanceComponent;
+use super::{
+    get_all_zone_file_names, zone_file_path, ZoneComponent, ZoneDirty, ZoneEvent, ZoneFilePath,
+};
+use crate::project::editor_project;
+
+/*
+
+Polls the zones folder for files that were changed outside of the editor, for example by a
+git pull or a script.  Loaded zones whose file changed are listed in the Zones window and can
+be reloaded in place.  Zones that also have unsaved changes are flagged as a conflict.
+Instances of a changed zone are always respawned, they have no edits of their own to lose.
+
+Saving a zone records the new modified time so the editor does not flag its own writes.
+
+*/
+
+const ZONE_FILE_POLL_INTERVAL_SECS: f32 = 1.0;
+
+#[derive(Resource)]
+pub struct ZoneFileWatcher {
+    poll_timer: Timer,
+    //last modified time seen for each watched zone file path
+    modified_times: HashMap<String, SystemTime>,
+    //loaded zones whose file changed on disk and have not been reloaded or dismissed
+    pub changed_zones: Vec<Entity>,
+    //reload clean zones as soon as their file changes instead of asking first
+    pub auto_reload: bool,
+    zone_file_names: Vec<String>,
+    //bumped whenever a zone file is added to or removed from the zones folder
+    pub file_list_generation: u64,
+}
+
+impl Default for ZoneFileWatcher {
+    fn default() -> Self {
+        Self {
+            poll_timer: Timer::from_seconds(ZONE_FILE_POLL_INTERVAL_SECS, TimerMode::Repeating),
+            modified_times: HashMap::new(),
+            changed_zones: Vec::new(),
+            auto_reload: false,
+            zone_file_names: Vec::new(),
+            file_list_generation: 0,
+        }
+    }
+}
+
+impl ZoneFileWatcher {
+    /// Remembers the current modified time of a file the editor just wrote.
+    pub fn record_file(&mut self, file_path: &str) {
+        if let Some(modified) = file_modified_time(file_path) {
+            self.modified_times.insert(file_path.to_string(), modified);
+        }
+    }
+
+    pub fn dismiss(&mut self, zone_entity: Entity) {
+        self.changed_zones.retain(|z| *z != zone_entity);
+    }
+}
+
+fn file_modified_time(file_path: &str) -> Option<SystemTime> {
+    fs::metadata(file_path).and_then(|m| m.modified()).ok()
+}
+
+pub fn watch_zone_files(
+    time: Res<Time>,
+    mut zone_file_watcher: ResMut<ZoneFileWatcher>,
+    loaded_zone_query: Query<(Entity, &ZoneFilePath, Has<ZoneDirty>), With<ZoneComponent>>,
+    zone_instance_query: Query<&ZoneInstanceComponent>,
+    mut zone_event_writer: EventWriter<ZoneEvent>,
+) {
+    if !zone_file_watcher.poll_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let zone_file_names = match editor_project().zones_folder_path().is_dir() {
+        true => get_all_zone_file_names(),
+        false => Vec::new(),
+    };
+    if zone_file_names != zone_file_watcher.zone_file_names {
+        zone_file_watcher.zone_file_names = zone_file_names;
+        zone_file_watcher.file_list_generation += 1;
+    }
+
+    //forget zones that were unloaded since the last poll
+    zone_file_watcher
+        .changed_zones
+        .retain(|zone| loaded_zone_query.contains(*zone));
+
+    let mut instanced_zones: Vec<String> = zone_instance_query
+        .iter()
+        .map(|zone_instance| zone_instance.zone_name.clone())
+        .collect();
+    instanced_zones.sort();
+    instanced_zones.dedup();
+
+    let mut watched_files: Vec<String> = loaded_zone_query
+        .iter()
+        .map(|(_, file_path, _)| file_path.0.clone())
+        .chain(instanced_zones.iter().map(|zone_name| zone_file_path(zone_name)))
+        .collect();
+    watched_files.sort();
+    watched_files.dedup();
+
+    let mut changed_files: Vec<String> = Vec::new();
+
+    for file_path in watched_files {
+        //deleted files are handled by the zone file browser
+        let Some(modified) = file_modified_time(&file_path) else {
+            continue;
+        };
+
+        let previous = zone_file_watcher
+            .modified_times
+            .insert(file_path.clone(), modified);
+
+        //the first time a file is seen just records it
+        if previous.map_or(true, |previous| previous == modified) {
+            continue;
+        }
+
+        println!("zone file {:?} changed on disk", file_path);
+        changed_files.push(file_path);
+    }
+
+    for zone_name in instanced_zones {
+        if changed_files.contains(&zone_file_path(&zone_name)) {
+            zone_event_writer.send(ZoneEvent::RefreshZoneInstances(zone_name));
+        }
+    }
+
+    for (zone_entity, file_path, dirty) in loaded_zone_query.iter() {
+        if !changed_files.contains(&file_path.0) {
+            continue;
+        }
+
+        if zone_file_watcher.auto_reload && !dirty {
+            zone_event_writer.send(ZoneEvent::ReloadZoneFromDisk(zone_entity));
+            continue;
+        }
+
+        if !zone_file_watcher.changed_zones.contains(&zone_entity) {
+            zone_file_watcher.changed_zones.push(zone_entity);
+        }
+    }
+}
+
+/// Lists zones whose file changed on disk, for the Zones window.
+pub(crate) fn changed_zone_files_ui(world: &mut World, ui: &mut egui::Ui) {
+    let changed_zones = world.resource::<ZoneFileWatcher>().changed_zones.clone();
+
+    let mut auto_reload = world.resource::<ZoneFileWatcher>().auto_reload;
+    if ui
+        .checkbox(&mut auto_reload, "Reload zones changed on disk automatically")
+        .on_hover_text("Zones with unsaved changes always ask first")
+        .changed()
+    {
+        world.resource_mut::<ZoneFileWatcher>().auto_reload = auto_reload;
+    }
+
+    if changed_zones.is_empty() {
+        return;
+    }
+
+    ui.label(RichText::new("Zone files changed on disk:").color(egui::Color32::YELLOW));
+
+    for zone_entity in changed_zones {
+        let Some(name) = world.get::<Name>(zone_entity).map(|n| n.as_str().to_string()) else {
+            continue;
+        };
+        let conflict = world.get::<ZoneDirty>(zone_entity).is_some();
+
+        ui.horizontal(|ui| {
+            match conflict {
+                true => ui.label(
+                    RichText::new(format!(
+                        "{} also has unsaved changes in the editor, reloading discards them",
+                        name
+                    ))
+                    .color(egui::Color32::RED),
+                ),
+                false => ui.label(name.clone()),
+            };
+
+            let reload_label = match conflict {
+                true => "Reload (discard mine)",
+                false => "Reload",
+            };
+            if ui.button(reload_label).clicked() {
+                world.send_event::<ZoneEvent>(ZoneEvent::ReloadZoneFromDisk(zone_entity));
+            }
+
+            let ignore_label = match conflict {
+                true => "Keep mine",
+                false => "Ignore",
+            };
+            if ui.button(ignore_label).clicked() {
+                world
+                    .resource_mut::<ZoneFileWatcher>()
+                    .dismiss(zone_entity);
+            }
+        });
+    }
+}
@@ -0,0 +1,597 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde_json::{json, Map, Value};
+
+use crate::doodads::doodad::DoodadComponent;
+use crate::doodads::doodad_id::DoodadId;
+use crate::doodads::doodad_manifest::RenderableType;
+use crate::project::editor_project;
+
+use super::zone_file::{CustomPropsComponent, ZoneMetadataComponent};
+use super::zone_file_stem;
+
+/*
+
+Writes a zone to a binary glTF (.glb) so the layout can be opened in Blender or other DCC tools.
+
+Every doodad becomes a node at its world transform, named after the doodad, with its doodad
+name, model, id and custom props in the node extras.  Doodads with a GltfModel get a copy of the
+model scene as children.  Each model file is embedded once and every doodad using it shares the
+same meshes, so they import as linked duplicates.  Other renderable types, and models that
+could not be read, are left as empty nodes.
+
+Only what is needed for a static layout is copied from the models: meshes, materials, textures
+and images.  Animations, skins, cameras and lights are dropped.
+
+*/
+
+type GltfExportResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+const GLB_MAGIC: u32 = 0x4654_6C67; //"glTF"
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A; //"JSON"
+const GLB_BIN_CHUNK: u32 = 0x004E_4942; //"BIN\0"
+
+//extensions that reference something this exporter does not copy
+const DROPPED_GLTF_EXTENSIONS: [&str; 2] = ["KHR_lights_punctual", "KHR_draco_mesh_compression"];
+
+/// Summary of an export, for the status line of the Zones window.
+pub struct ZoneGltfExport {
+    pub file_path: PathBuf,
+    pub doodad_count: usize,
+    //doodads exported as empty nodes because their model could not be embedded
+    pub warnings: Vec<String>,
+}
+
+/// Exports a loaded zone to `<export folder>/<zone name>.glb`.
+pub fn export_zone_to_gltf(world: &mut World, zone_entity: Entity) -> GltfExportResult<ZoneGltfExport> {
+    let zone_name = world
+        .get::<Name>(zone_entity)
+        .map(|name| zone_file_stem(name.as_str()))
+        .ok_or("Zone has no name")?;
+
+    let mut scene_writer = GltfSceneWriter::default();
+    let mut warnings: Vec<String> = Vec::new();
+
+    let mut doodad_entities: Vec<Entity> = Vec::new();
+    let mut stack: Vec<Entity> = vec![zone_entity];
+    while let Some(entity) = stack.pop() {
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.iter().rev());
+        }
+        if world.get::<DoodadComponent>(entity).is_some() {
+            doodad_entities.push(entity);
+        }
+    }
+
+    let mut zone_children: Vec<usize> = Vec::new();
+
+    for entity in doodad_entities.iter() {
+        let (Some(name), Some(doodad), Some(global_transform)) = (
+            world.get::<Name>(*entity),
+            world.get::<DoodadComponent>(*entity),
+            world.get::<GlobalTransform>(*entity),
+        ) else {
+            continue;
+        };
+
+        let mut extras = Map::new();
+        extras.insert("doodad_name".into(), json!(name.as_str()));
+        if let Some(doodad_id) = world.get::<DoodadId>(*entity) {
+            extras.insert("doodad_id".into(), json!(doodad_id.to_string()));
+        }
+        if let Some(custom_props) = world.get::<CustomPropsComponent>(*entity) {
+            let sorted_props: BTreeMap<_, _> = custom_props.props.iter().collect();
+            extras.insert("custom_props".into(), serde_json::to_value(sorted_props)?);
+        }
+
+        let model_roots = match &doodad.definition.model {
+            RenderableType::GltfModel(model_path) => {
+                extras.insert("model".into(), json!(model_path));
+
+                match scene_writer.embed_model(model_path) {
+                    Ok(roots) => roots,
+                    Err(e) => {
+                        warnings.push(format!("{}: could not embed {:?}: {}", name, model_path, e));
+                        Vec::new()
+                    }
+                }
+            }
+            other => {
+                extras.insert("renderable".into(), serde_json::to_value(other)?);
+                Vec::new()
+            }
+        };
+
+        //every doodad is at the root of the zone with its world transform
+        let (scale, rotation, translation) = global_transform.to_scale_rotation_translation();
+
+        let mut doodad_node = json!({
+            "name": name.as_str(),
+            "translation": translation.to_array(),
+            "rotation": rotation.to_array(),
+            "scale": scale.to_array(),
+            "extras": extras,
+        });
+        //glTF does not allow an empty children list
+        if !model_roots.is_empty() {
+            doodad_node["children"] = json!(model_roots);
+        }
+
+        let doodad_node = scene_writer.push_node(doodad_node);
+        zone_children.push(doodad_node);
+    }
+
+    let mut zone_extras = Map::new();
+    zone_extras.insert("zone_name".into(), json!(zone_name));
+    if let Some(metadata) = world.get::<ZoneMetadataComponent>(zone_entity) {
+        zone_extras.insert("metadata".into(), serde_json::to_value(&metadata.metadata)?);
+    }
+
+    let mut zone_node = json!({
+        "name": zone_name,
+        "extras": zone_extras,
+    });
+    if !zone_children.is_empty() {
+        zone_node["children"] = json!(zone_children);
+    }
+
+    let zone_node = scene_writer.push_node(zone_node);
+
+    let export_folder = editor_project().export_folder_path();
+    fs::create_dir_all(&export_folder)?;
+
+    let file_path = export_folder.join(format!("{}.glb", zone_name));
+    fs::write(&file_path, scene_writer.to_glb(&zone_name, zone_node)?)?;
+
+    for warning in warnings.iter() {
+        eprintln!("{}", warning);
+    }
+    println!("exported zone {:?} to {:?}", zone_name, file_path);
+
+    Ok(ZoneGltfExport {
+        file_path,
+        doodad_count: zone_children.len(),
+        warnings,
+    })
+}
+
+/// A model file copied into the export, its nodes are copied again for every doodad using it.
+struct EmbeddedModel {
+    //children indices are into this list
+    nodes: Vec<Value>,
+    roots: Vec<usize>,
+}
+
+/// Sizes of the document arrays and the buffer, to undo a model that could not be read.
+struct WriterCheckpoint {
+    array_lens: Vec<(String, usize)>,
+    bin_len: usize,
+}
+
+/// Builds a single glTF document with one binary buffer.
+#[derive(Default)]
+struct GltfSceneWriter {
+    root: Map<String, Value>,
+    bin: Vec<u8>,
+    //by model path, models that could not be read keep their error
+    embedded_models: HashMap<String, Result<EmbeddedModel, String>>,
+    extensions_used: Vec<String>,
+}
+
+impl GltfSceneWriter {
+    fn array_mut(&mut self, key: &str) -> &mut Vec<Value> {
+        let value = self
+            .root
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Array(Vec::new()));
+
+        match value {
+            Value::Array(array) => array,
+            _ => unreachable!("glTF top level {} is always an array", key),
+        }
+    }
+
+    fn push(&mut self, key: &str, value: Value) -> usize {
+        let array = self.array_mut(key);
+        array.push(value);
+        array.len() - 1
+    }
+
+    fn len(&self, key: &str) -> usize {
+        self.root
+            .get(key)
+            .and_then(|value| value.as_array())
+            .map_or(0, |array| array.len())
+    }
+
+    fn push_node(&mut self, node: Value) -> usize {
+        self.push("nodes", node)
+    }
+
+    //appends bytes to the buffer, 4 byte aligned as accessors require
+    fn push_bin(&mut self, bytes: &[u8]) -> usize {
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        let offset = self.bin.len();
+        self.bin.extend_from_slice(bytes);
+        offset
+    }
+
+    fn push_buffer_view(&mut self, bytes: &[u8]) -> usize {
+        let offset = self.push_bin(bytes);
+        self.push(
+            "bufferViews",
+            json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len() }),
+        )
+    }
+
+    fn checkpoint(&self) -> WriterCheckpoint {
+        WriterCheckpoint {
+            array_lens: self
+                .root
+                .iter()
+                .filter_map(|(key, value)| value.as_array().map(|array| (key.clone(), array.len())))
+                .collect(),
+            bin_len: self.bin.len(),
+        }
+    }
+
+    //drops everything pushed since the checkpoint
+    fn rollback(&mut self, checkpoint: WriterCheckpoint) {
+        self.root
+            .retain(|key, _| checkpoint.array_lens.iter().any(|(checkpoint_key, _)| checkpoint_key == key));
+        for (key, len) in checkpoint.array_lens {
+            self.array_mut(&key).truncate(len);
+        }
+        self.bin.truncate(checkpoint.bin_len);
+    }
+
+    /// Copies the nodes of a model into the scene, embedding the model the first time.
+    /// Returns the new root nodes.
+    fn embed_model(&mut self, model_path: &str) -> GltfExportResult<Vec<usize>> {
+        if !self.embedded_models.contains_key(model_path) {
+            //a model that fails halfway must not leave its buffers and accessors behind
+            let checkpoint = self.checkpoint();
+            let embedded_model = match self.read_model(model_path) {
+                Ok(embedded_model) => Ok(embedded_model),
+                Err(e) => {
+                    self.rollback(checkpoint);
+                    Err(e.to_string())
+                }
+            };
+            self.embedded_models
+                .insert(model_path.to_string(), embedded_model);
+        }
+
+        let embedded_model = match self.embedded_models.get(model_path) {
+            Some(Ok(embedded_model)) => embedded_model,
+            Some(Err(error)) => return Err(error.clone().into()),
+            None => unreachable!(),
+        };
+
+        let base = self.len("nodes");
+        let nodes: Vec<Value> = embedded_model
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut node = node.clone();
+                offset_index_array(&mut node, "children", base);
+                node
+            })
+            .collect();
+        let roots: Vec<usize> = embedded_model.roots.iter().map(|root| root + base).collect();
+
+        self.array_mut("nodes").extend(nodes);
+
+        Ok(roots)
+    }
+
+    //merges the meshes, materials and textures of a model file into this document
+    fn read_model(&mut self, model_path: &str) -> GltfExportResult<EmbeddedModel> {
+        //asset paths can carry a label like #Scene0
+        let relative_path = model_path.split('#').next().unwrap_or(model_path);
+        let file_path = editor_project().asset_folder.join(relative_path);
+
+        let file_bytes = fs::read(&file_path)?;
+        let (document, glb_bin) = parse_gltf(&file_bytes)?;
+        let model_folder = file_path.parent().unwrap_or(Path::new(""));
+
+        let source_array = |key: &str| -> Vec<Value> {
+            document
+                .get(key)
+                .and_then(|value| value.as_array())
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        //buffers are appended to the single output buffer
+        let mut buffer_offsets: Vec<usize> = Vec::new();
+        for buffer in source_array("buffers") {
+            let bytes = match buffer.get("uri").and_then(|uri| uri.as_str()) {
+                Some(uri) => read_gltf_uri(uri, model_folder)?,
+                None => glb_bin.clone().ok_or("glb buffer without a BIN chunk")?,
+            };
+            buffer_offsets.push(self.push_bin(&bytes));
+        }
+
+        let buffer_view_base = self.len("bufferViews");
+        for mut buffer_view in source_array("bufferViews") {
+            let buffer = buffer_view["buffer"].as_u64().unwrap_or(0) as usize;
+            let offset = buffer_view["byteOffset"].as_u64().unwrap_or(0) as usize;
+            let buffer_offset = *buffer_offsets.get(buffer).ok_or("bufferView without a buffer")?;
+
+            buffer_view["buffer"] = json!(0);
+            buffer_view["byteOffset"] = json!(buffer_offset + offset);
+            self.push("bufferViews", buffer_view);
+        }
+
+        let accessor_base = self.len("accessors");
+        for mut accessor in source_array("accessors") {
+            offset_index(&mut accessor, "bufferView", buffer_view_base);
+            if let Some(sparse) = accessor.get_mut("sparse") {
+                offset_index(&mut sparse["indices"], "bufferView", buffer_view_base);
+                offset_index(&mut sparse["values"], "bufferView", buffer_view_base);
+            }
+            self.push("accessors", accessor);
+        }
+
+        //images that point at files are embedded, the export is a single file
+        let image_base = self.len("images");
+        for mut image in source_array("images") {
+            match image.get("uri").and_then(|uri| uri.as_str()).map(|uri| uri.to_string()) {
+                Some(uri) => {
+                    let bytes = read_gltf_uri(&uri, model_folder)?;
+                    let mime_type = match uri.to_lowercase().ends_with(".png") {
+                        true => "image/png",
+                        false => "image/jpeg",
+                    };
+                    let buffer_view = self.push_buffer_view(&bytes);
+
+                    image = json!({
+                        "name": image.get("name").cloned().unwrap_or(json!(uri)),
+                        "bufferView": buffer_view,
+                        "mimeType": image.get("mimeType").cloned().unwrap_or(json!(mime_type)),
+                    });
+                }
+                None => offset_index(&mut image, "bufferView", buffer_view_base),
+            }
+            self.push("images", image);
+        }
+
+        let sampler_base = self.len("samplers");
+        for sampler in source_array("samplers") {
+            self.push("samplers", sampler);
+        }
+
+        let texture_base = self.len("textures");
+        for mut texture in source_array("textures") {
+            offset_index(&mut texture, "source", image_base);
+            offset_index(&mut texture, "sampler", sampler_base);
+            self.push("textures", texture);
+        }
+
+        let material_base = self.len("materials");
+        for mut material in source_array("materials") {
+            offset_texture_infos(&mut material, texture_base);
+            self.push("materials", material);
+        }
+
+        let mesh_base = self.len("meshes");
+        for mut mesh in source_array("meshes") {
+            if let Some(primitives) = mesh.get_mut("primitives").and_then(|p| p.as_array_mut()) {
+                for primitive in primitives.iter_mut() {
+                    if let Some(attributes) = primitive.get_mut("attributes") {
+                        offset_index_map(attributes, accessor_base);
+                    }
+                    if let Some(targets) = primitive.get_mut("targets").and_then(|t| t.as_array_mut()) {
+                        for target in targets.iter_mut() {
+                            offset_index_map(target, accessor_base);
+                        }
+                    }
+                    offset_index(primitive, "indices", accessor_base);
+                    offset_index(primitive, "material", material_base);
+                    remove_dropped_extensions(primitive);
+                }
+            }
+            self.push("meshes", mesh);
+        }
+
+        //nodes keep their indices local to the model, they are offset when copied per doodad
+        let nodes: Vec<Value> = source_array("nodes")
+            .into_iter()
+            .map(|mut node| {
+                if let Some(node) = node.as_object_mut() {
+                    node.remove("skin");
+                    node.remove("camera");
+                    node.remove("weights");
+                }
+                offset_index(&mut node, "mesh", mesh_base);
+                remove_dropped_extensions(&mut node);
+                node
+            })
+            .collect();
+
+        let scene_index = document["scene"].as_u64().unwrap_or(0) as usize;
+        let roots: Vec<usize> = match document["scenes"].get(scene_index) {
+            Some(scene) => scene["nodes"]
+                .as_array()
+                .map(|nodes| nodes.iter().filter_map(|n| n.as_u64()).map(|n| n as usize).collect())
+                .unwrap_or_default(),
+            //a file without scenes still has its parentless nodes
+            None => {
+                let child_nodes: Vec<u64> = nodes
+                    .iter()
+                    .filter_map(|node| node["children"].as_array())
+                    .flatten()
+                    .filter_map(|n| n.as_u64())
+                    .collect();
+                (0..nodes.len())
+                    .filter(|n| !child_nodes.contains(&(*n as u64)))
+                    .collect()
+            }
+        };
+
+        for extension in document["extensionsUsed"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|extension| extension.as_str())
+        {
+            if !DROPPED_GLTF_EXTENSIONS.contains(&extension)
+                && !self.extensions_used.iter().any(|e| e == extension)
+            {
+                self.extensions_used.push(extension.to_string());
+            }
+        }
+
+        Ok(EmbeddedModel { nodes, roots })
+    }
+
+    fn to_glb(mut self, scene_name: &str, root_node: usize) -> GltfExportResult<Vec<u8>> {
+        self.root.insert(
+            "asset".into(),
+            json!({ "version": "2.0", "generator": "bevy_mesh_terrain_editor zone export" }),
+        );
+        self.root.insert("scene".into(), json!(0));
+        self.root.insert(
+            "scenes".into(),
+            json!([{ "name": scene_name, "nodes": [root_node] }]),
+        );
+
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        if !self.bin.is_empty() {
+            self.root
+                .insert("buffers".into(), json!([{ "byteLength": self.bin.len() }]));
+        }
+        if !self.extensions_used.is_empty() {
+            self.root
+                .insert("extensionsUsed".into(), json!(self.extensions_used));
+        }
+
+        let mut json_chunk = serde_json::to_vec(&self.root)?;
+        while json_chunk.len() % 4 != 0 {
+            json_chunk.push(b' ');
+        }
+
+        let mut total_length = 12 + 8 + json_chunk.len();
+        if !self.bin.is_empty() {
+            total_length += 8 + self.bin.len();
+        }
+
+        let mut glb: Vec<u8> = Vec::with_capacity(total_length);
+        glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+
+        glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_JSON_CHUNK.to_le_bytes());
+        glb.extend_from_slice(&json_chunk);
+
+        if !self.bin.is_empty() {
+            glb.extend_from_slice(&(self.bin.len() as u32).to_le_bytes());
+            glb.extend_from_slice(&GLB_BIN_CHUNK.to_le_bytes());
+            glb.extend_from_slice(&self.bin);
+        }
+
+        Ok(glb)
+    }
+}
+
+/// The JSON document of a .gltf or .glb file, and the BIN chunk of a .glb.
+pub(crate) fn parse_gltf(bytes: &[u8]) -> GltfExportResult<(Value, Option<Vec<u8>>)> {
+    let read_u32 = |offset: usize| -> GltfExportResult<u32> {
+        let word = bytes.get(offset..offset + 4).ok_or("truncated glb file")?;
+        Ok(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+    };
+
+    if bytes.len() < 12 || read_u32(0)? != GLB_MAGIC {
+        return Ok((serde_json::from_slice(bytes)?, None));
+    }
+
+    let mut document: Option<Value> = None;
+    let mut bin: Option<Vec<u8>> = None;
+
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let chunk_length = read_u32(offset)? as usize;
+        let chunk_type = read_u32(offset + 4)?;
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or("truncated glb chunk")?;
+
+        match chunk_type {
+            GLB_JSON_CHUNK => document = Some(serde_json::from_slice(chunk)?),
+            GLB_BIN_CHUNK => bin = Some(chunk.to_vec()),
+            _ => {}
+        }
+
+        offset += 8 + chunk_length;
+    }
+
+    Ok((document.ok_or("glb file without a JSON chunk")?, bin))
+}
+
+//a data uri, or a path relative to the gltf file
+fn read_gltf_uri(uri: &str, folder: &Path) -> GltfExportResult<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data.split_once(";base64,").ok_or("unsupported data uri")?;
+        return Ok(base64::engine::general_purpose::STANDARD.decode(encoded)?);
+    }
+
+    let path = uri.replace("%20", " ");
+    Ok(fs::read(folder.join(path))?)
+}
+
+fn offset_index(value: &mut Value, key: &str, base: usize) {
+    if let Some(index) = value.get(key).and_then(|index| index.as_u64()) {
+        value[key] = json!(index as usize + base);
+    }
+}
+
+fn offset_index_array(value: &mut Value, key: &str, base: usize) {
+    if let Some(indices) = value.get_mut(key).and_then(|indices| indices.as_array_mut()) {
+        for index in indices.iter_mut() {
+            if let Some(old_index) = index.as_u64() {
+                *index = json!(old_index as usize + base);
+            }
+        }
+    }
+}
+
+fn offset_index_map(value: &mut Value, base: usize) {
+    if let Some(map) = value.as_object_mut() {
+        for index in map.values_mut() {
+            if let Some(old_index) = index.as_u64() {
+                *index = json!(old_index as usize + base);
+            }
+        }
+    }
+}
+
+//texture references in materials and their extensions are objects under a key ending in Texture
+fn offset_texture_infos(value: &mut Value, texture_base: usize) {
+    if let Some(map) = value.as_object_mut() {
+        for (key, child) in map.iter_mut() {
+            if key.ends_with("Texture") || key.ends_with("texture") {
+                offset_index(child, "index", texture_base);
+            }
+            offset_texture_infos(child, texture_base);
+        }
+    }
+}
+
+fn remove_dropped_extensions(value: &mut Value) {
+    if let Some(extensions) = value.get_mut("extensions").and_then(|e| e.as_object_mut()) {
+        for extension in DROPPED_GLTF_EXTENSIONS {
+            extensions.remove(extension);
+        }
+    }
+}
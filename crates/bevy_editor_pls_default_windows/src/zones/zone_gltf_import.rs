@@ -0,0 +1,329 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde_json::Value;
+
+use crate::doodads::doodad_id::DoodadId;
+use crate::doodads::doodad_manifest::{DoodadManifest, DoodadManifestResource, RenderableType};
+use crate::doodads::PlaceDoodadEvent;
+use crate::zones::zone_file::{remap_custom_prop_doodad_references, CustomPropsMap};
+
+use super::zone_gltf_export::parse_gltf;
+use super::ZoneResource;
+
+/*
+
+Places doodads from a glTF scene, like a layout blocked out in Blender with the same models
+that are in the doodad manifest, or a zone exported with the glTF export.
+
+Each node is matched against the manifest by, in order:
+  the doodad name in its extras (written by the zone export)
+  the model path in its extras
+  the node name
+  the mesh name
+Names match a doodad name or the file name of a GltfModel path, ignoring case and the `.001`
+suffix Blender adds to duplicates.  A matched node is placed at its world transform and its
+children are skipped, they are the parts of the model.  Nodes with a mesh or without children
+that match nothing are reported.
+
+Placed doodads always get new ids, the file may come from a zone that is still loaded.  Doodad
+references between nodes that carry the exported id are pointed at the new ids.
+
+*/
+
+type GltfImportResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Summary of an import, for the Zones window.
+pub struct ZoneGltfImport {
+    pub file_path: PathBuf,
+    pub placed_count: usize,
+    pub unmatched_nodes: Vec<String>,
+}
+
+/// A node that matched a doodad, with its transform relative to the zone it is placed in.
+struct MatchedNode {
+    doodad_name: String,
+    transform: Transform,
+    custom_props: Option<CustomPropsMap>,
+    //id written by the zone export
+    id: Option<DoodadId>,
+}
+
+/// Lookup from names found in a glTF file to doodad names.
+struct DoodadMatcher {
+    by_doodad_name: HashMap<String, String>,
+    by_model: HashMap<String, String>,
+}
+
+impl DoodadMatcher {
+    fn new(manifest: &DoodadManifest) -> Self {
+        let mut doodad_names: Vec<&String> = manifest.doodad_definitions.keys().collect();
+        doodad_names.sort();
+
+        let mut by_doodad_name = HashMap::new();
+        let mut by_model = HashMap::new();
+
+        for doodad_name in doodad_names {
+            by_doodad_name.insert(doodad_name.to_lowercase(), doodad_name.clone());
+
+            let Some(RenderableType::GltfModel(model_path)) = manifest
+                .doodad_definitions
+                .get(doodad_name)
+                .map(|definition| &definition.model)
+            else {
+                continue;
+            };
+
+            //several doodads can share a model, the first by name wins
+            by_model
+                .entry(model_path_key(model_path))
+                .or_insert_with(|| doodad_name.clone());
+        }
+
+        Self {
+            by_doodad_name,
+            by_model,
+        }
+    }
+
+    fn match_name(&self, name: &str) -> Option<String> {
+        let key = strip_duplicate_suffix(name).to_lowercase();
+
+        self.by_doodad_name
+            .get(&key)
+            .or_else(|| self.by_model.get(&key))
+            .cloned()
+    }
+
+    fn match_model(&self, model_path: &str) -> Option<String> {
+        self.by_model.get(&model_path_key(model_path)).cloned()
+    }
+
+    fn match_node(&self, node: &Value, document: &Value) -> Option<String> {
+        let extras = &node["extras"];
+
+        let mesh_name = node["mesh"]
+            .as_u64()
+            .and_then(|mesh| document["meshes"][mesh as usize]["name"].as_str());
+
+        extras["doodad_name"]
+            .as_str()
+            .and_then(|name| self.match_name(name))
+            .or_else(|| extras["model"].as_str().and_then(|model| self.match_model(model)))
+            .or_else(|| node["name"].as_str().and_then(|name| self.match_name(name)))
+            .or_else(|| mesh_name.and_then(|name| self.match_name(name)))
+    }
+}
+
+//file name of a model without its extension or label, lowercase
+fn model_path_key(model_path: &str) -> String {
+    let path = model_path.split('#').next().unwrap_or(model_path);
+
+    Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(path)
+        .to_lowercase()
+}
+
+//blender names duplicates rock, rock.001, rock.002 ...
+fn strip_duplicate_suffix(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((base, suffix))
+            if !base.is_empty() && !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => name,
+    }
+}
+
+fn node_local_matrix(node: &Value) -> Mat4 {
+    let read_floats = |key: &str| -> Option<Vec<f32>> {
+        node[key]
+            .as_array()
+            .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+    };
+
+    if let Some(matrix) = read_floats("matrix").filter(|m| m.len() == 16) {
+        return Mat4::from_cols_slice(&matrix);
+    }
+
+    let translation = read_floats("translation")
+        .filter(|t| t.len() == 3)
+        .map_or(Vec3::ZERO, |t| Vec3::from_slice(&t));
+    let rotation = read_floats("rotation")
+        .filter(|r| r.len() == 4)
+        .map_or(Quat::IDENTITY, |r| Quat::from_slice(&r).normalize());
+    let scale = read_floats("scale")
+        .filter(|s| s.len() == 3)
+        .map_or(Vec3::ONE, |s| Vec3::from_slice(&s));
+
+    Mat4::from_scale_rotation_translation(scale, rotation, translation)
+}
+
+fn node_label(node: &Value, node_index: usize) -> String {
+    node["name"]
+        .as_str()
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("node {}", node_index))
+}
+
+fn collect_matched_nodes(
+    document: &Value,
+    matcher: &DoodadMatcher,
+    node_index: usize,
+    parent_matrix: Mat4,
+    //world to zone local space
+    zone_inverse_matrix: Mat4,
+    matched_nodes: &mut Vec<MatchedNode>,
+    unmatched_nodes: &mut Vec<String>,
+) {
+    let node = &document["nodes"][node_index];
+    if node.is_null() {
+        return;
+    }
+
+    let world_matrix = parent_matrix * node_local_matrix(node);
+
+    if let Some(doodad_name) = matcher.match_node(node, document) {
+        //props written by the zone export, anything unreadable falls back to the manifest defaults
+        let custom_props = node["extras"]
+            .get("custom_props")
+            .and_then(|props| serde_json::from_value::<CustomPropsMap>(props.clone()).ok());
+
+        let id = node["extras"]["doodad_id"]
+            .as_str()
+            .and_then(|id| u64::from_str_radix(id, 16).ok())
+            .map(DoodadId);
+
+        matched_nodes.push(MatchedNode {
+            doodad_name,
+            transform: Transform::from_matrix(zone_inverse_matrix * world_matrix),
+            custom_props,
+            id,
+        });
+        return;
+    }
+
+    let children: Vec<usize> = node["children"]
+        .as_array()
+        .map(|children| children.iter().filter_map(|c| c.as_u64()).map(|c| c as usize).collect())
+        .unwrap_or_default();
+
+    if node.get("mesh").is_some() || children.is_empty() {
+        let mut label = node_label(node, node_index);
+        if let Some(mesh_name) = node["mesh"]
+            .as_u64()
+            .and_then(|mesh| document["meshes"][mesh as usize]["name"].as_str())
+        {
+            label = format!("{} (mesh {})", label, mesh_name);
+        }
+        unmatched_nodes.push(label);
+    }
+
+    for child in children {
+        collect_matched_nodes(
+            document,
+            matcher,
+            child,
+            world_matrix,
+            zone_inverse_matrix,
+            matched_nodes,
+            unmatched_nodes,
+        );
+    }
+}
+
+/// Places every doodad found in a .gltf or .glb file into the primary zone.
+pub fn import_gltf_layout(
+    world: &mut World,
+    file_path: impl AsRef<Path>,
+) -> GltfImportResult<ZoneGltfImport> {
+    let file_path = file_path.as_ref();
+
+    let primary_zone = world
+        .resource::<ZoneResource>()
+        .primary_zone
+        .filter(|zone| world.get_entity(*zone).is_some())
+        .ok_or("Set a primary zone to import into first")?;
+
+    let manifest = world
+        .resource::<DoodadManifestResource>()
+        .manifest
+        .as_ref()
+        .and_then(|handle| world.resource::<Assets<DoodadManifest>>().get(handle))
+        .ok_or("The doodad manifest is not loaded")?;
+    let matcher = DoodadMatcher::new(manifest);
+
+    let (document, _) = parse_gltf(&fs::read(file_path)?)?;
+
+    let scene_index = document["scene"].as_u64().unwrap_or(0) as usize;
+    let root_nodes: Vec<usize> = match document["scenes"][scene_index]["nodes"].as_array() {
+        Some(nodes) => nodes.iter().filter_map(|n| n.as_u64()).map(|n| n as usize).collect(),
+        None => return Err("glTF file has no scene".into()),
+    };
+
+    //the file is in world space, doodads are placed relative to the zone
+    let zone_inverse_matrix = world
+        .get::<GlobalTransform>(primary_zone)
+        .map_or(Mat4::IDENTITY, |global| global.compute_matrix().inverse());
+
+    let mut matched_nodes: Vec<MatchedNode> = Vec::new();
+    let mut unmatched_nodes: Vec<String> = Vec::new();
+
+    for root_node in root_nodes {
+        collect_matched_nodes(
+            &document,
+            &matcher,
+            root_node,
+            Mat4::IDENTITY,
+            zone_inverse_matrix,
+            &mut matched_nodes,
+            &mut unmatched_nodes,
+        );
+    }
+
+    let placed_count = matched_nodes.len();
+
+    let new_ids: HashMap<DoodadId, DoodadId> = matched_nodes
+        .iter()
+        .filter_map(|matched_node| matched_node.id)
+        .map(|id| (id, DoodadId::new_random()))
+        .collect();
+
+    //sent together so they are placed as a single history entry
+    world.send_event_batch(matched_nodes.into_iter().map(|matched_node| PlaceDoodadEvent {
+        position: matched_node.transform.translation,
+        scale: Some(matched_node.transform.scale),
+        rotation_euler: None,
+        rotation: Some(matched_node.transform.rotation),
+        doodad_name: matched_node.doodad_name,
+        custom_props: matched_node.custom_props.map(|mut props| {
+            remap_custom_prop_doodad_references(&mut props, &new_ids);
+            props
+        }),
+        zone: None,
+        parent: None,
+        reserved_entity: None,
+        id: matched_node.id.and_then(|id| new_ids.get(&id).copied()),
+    }));
+
+    for unmatched_node in unmatched_nodes.iter() {
+        eprintln!("glTF import: no doodad matches {}", unmatched_node);
+    }
+    println!(
+        "imported {} doodads from {:?}, {} nodes did not match",
+        placed_count,
+        file_path,
+        unmatched_nodes.len()
+    );
+
+    Ok(ZoneGltfImport {
+        file_path: file_path.to_path_buf(),
+        placed_count,
+        unmatched_nodes,
+    })
+}
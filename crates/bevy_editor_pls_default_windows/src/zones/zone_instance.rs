@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui::{self, RichText};
+
+use crate::doodads::PlaceDoodadEvent;
+
+use super::zone_file::ZoneInstance;
+use super::zone_file_format::read_zone_file;
+use super::{
+    get_all_zone_file_names, spawn_zone_entities, zone_file_path, zone_file_stem, ZoneEvent,
+};
+
+/*
+
+A zone instance is another zone file spawned inside a zone under an instance node.
+
+Only the instance node (which zone, and its transform) is saved with the host zone.  Its
+doodads are always read from the referenced zone file, so saving or changing that file
+respawns every instance of it.  Instances can nest, and a zone that ends up containing
+itself is reported instead of being spawned.
+
+*/
+
+/// The node an instanced zone is spawned under.
+#[derive(Component, Clone, Debug)]
+pub struct ZoneInstanceComponent {
+    pub zone_name: String,
+}
+
+/// The innermost zone instance an entity was spawned by.  Such entities are read-only in the
+/// editor: they are never saved with the host zone, only their source zone file is.
+pub fn owning_zone_instance(world: &World, entity: Entity) -> Option<&ZoneInstanceComponent> {
+    let mut current_entity = entity;
+    while let Some(parent) = world.get::<Parent>(current_entity) {
+        current_entity = parent.get();
+        if let Some(zone_instance) = world.get::<ZoneInstanceComponent>(current_entity) {
+            return Some(zone_instance);
+        }
+    }
+    None
+}
+
+/// Problems found while spawning zone instances, like reference cycles or missing files.
+#[derive(Resource, Default)]
+pub struct ZoneInstanceResource {
+    pub errors: Vec<String>,
+}
+
+/// Spawns an instance node under `parent` along with the contents of the referenced zone.
+/// `chain` holds the zones this one is nested in, outermost first.
+pub(crate) fn spawn_zone_instance(
+    commands: &mut Commands,
+    spawn_doodad_event_writer: &mut EventWriter<PlaceDoodadEvent>,
+    zone_instance: &ZoneInstance,
+    host_zone: Entity,
+    parent: Entity,
+    chain: &mut Vec<String>,
+    errors: &mut Vec<String>,
+) -> Option<Entity> {
+    let zone_name = zone_file_stem(&zone_instance.zone_name);
+
+    if let Some(error) = find_instance_cycle(chain, &zone_name) {
+        eprintln!("{}", error);
+        errors.push(error);
+        return None;
+    }
+
+    let instance_node = commands
+        .spawn(SpatialBundle {
+            transform: zone_instance.transform.clone().into(),
+            ..default()
+        })
+        .insert(Name::new(format!("{} (instance)", zone_name)))
+        .insert(ZoneInstanceComponent {
+            zone_name: zone_name.clone(),
+        })
+        .set_parent(parent)
+        .id();
+
+    spawn_zone_instance_contents(
+        commands,
+        spawn_doodad_event_writer,
+        instance_node,
+        &zone_name,
+        host_zone,
+        chain,
+        errors,
+    );
+
+    Some(instance_node)
+}
+
+/// Spawns the doodads and nested instances of a zone file under an existing instance node.
+pub(crate) fn spawn_zone_instance_contents(
+    commands: &mut Commands,
+    spawn_doodad_event_writer: &mut EventWriter<PlaceDoodadEvent>,
+    instance_node: Entity,
+    zone_name: &str,
+    host_zone: Entity,
+    chain: &mut Vec<String>,
+    errors: &mut Vec<String>,
+) {
+    let file_name = zone_file_path(zone_name);
+
+    let zone_file = match read_zone_file(&file_name) {
+        Ok((zone_file, _)) => zone_file,
+        Err(e) => {
+            let error = format!("Could not load instanced zone {:?}: {}", file_name, e);
+            eprintln!("{}", error);
+            errors.push(error);
+            return;
+        }
+    };
+
+    chain.push(zone_file_stem(zone_name));
+
+    spawn_zone_entities(
+        commands,
+        spawn_doodad_event_writer,
+        zone_file.entities,
+        host_zone,
+        Some(instance_node),
+    );
+
+    for nested_instance in zone_file.zone_instances.iter() {
+        spawn_zone_instance(
+            commands,
+            spawn_doodad_event_writer,
+            nested_instance,
+            host_zone,
+            instance_node,
+            chain,
+            errors,
+        );
+    }
+
+    chain.pop();
+}
+
+//an error describing the cycle if zone_name is already one of the zones it would be nested in
+fn find_instance_cycle(chain: &[String], zone_name: &str) -> Option<String> {
+    let cycle_start = chain.iter().position(|name| name == zone_name)?;
+
+    let mut cycle: Vec<&str> = chain[cycle_start..].iter().map(|name| name.as_str()).collect();
+    cycle.push(zone_name);
+
+    Some(format!("Zone instance cycle: {}", cycle.join(" -> ")))
+}
+
+/// The zones an entity is nested in, outermost first, for cycle checks.
+pub(crate) fn instance_chain(
+    entity: Entity,
+    zone_name: &str,
+    parent_query: &Query<&Parent>,
+    zone_instance_query: &Query<&ZoneInstanceComponent>,
+) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+
+    let mut current_entity = entity;
+    while let Ok(parent) = parent_query.get(current_entity) {
+        current_entity = parent.get();
+        if let Ok(zone_instance) = zone_instance_query.get(current_entity) {
+            chain.push(zone_instance.zone_name.clone());
+        }
+    }
+
+    chain.push(zone_file_stem(zone_name));
+    chain.reverse();
+
+    chain
+}
+
+#[derive(Default)]
+pub struct ZoneInstanceUiState {
+    add_zone_name: String,
+}
+
+/// Adding instances to the primary zone and the list of instance errors, for the Zones window.
+pub(crate) fn zone_instances_ui(
+    world: &mut World,
+    state: &mut ZoneInstanceUiState,
+    primary_zone: Option<Entity>,
+    ui: &mut egui::Ui,
+) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source("add_zone_instance")
+            .selected_text(match state.add_zone_name.is_empty() {
+                true => "Select zone",
+                false => state.add_zone_name.as_str(),
+            })
+            .show_ui(ui, |ui| {
+                for zone_file_name in get_all_zone_file_names() {
+                    let zone_name = zone_file_stem(&zone_file_name);
+                    ui.selectable_value(&mut state.add_zone_name, zone_name.clone(), zone_name);
+                }
+            });
+
+        let can_add = primary_zone.is_some() && !state.add_zone_name.is_empty();
+
+        if ui
+            .add_enabled(can_add, egui::Button::new("Add Instance To Primary Zone"))
+            .clicked()
+        {
+            if let Some(zone) = primary_zone {
+                world.send_event::<ZoneEvent>(ZoneEvent::AddZoneInstance {
+                    zone,
+                    zone_name: state.add_zone_name.clone(),
+                });
+            }
+        }
+    });
+
+    let errors = world.resource::<ZoneInstanceResource>().errors.clone();
+    if errors.is_empty() {
+        return;
+    }
+
+    for error in errors.iter() {
+        ui.label(RichText::new(error).color(egui::Color32::RED));
+    }
+    if ui.button("Dismiss").clicked() {
+        world.resource_mut::<ZoneInstanceResource>().errors.clear();
+    }
+}
@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+
+pub use bevy_zone_runtime::zone_migration::{load_and_migrate_zone_file, CURRENT_ZONE_FORMAT_VERSION};
+
+pub struct UpgradedZone {
+    pub zone_entity: Entity,
+    pub file_name: String,
+    pub from_version: u32,
+}
+
+/// Zones that were loaded from an older format and have not been re-saved yet.
+#[derive(Resource, Default)]
+pub struct ZoneMigrationResource {
+    pub upgraded_zones: Vec<UpgradedZone>,
+}
@@ -0,0 +1,214 @@
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy_inspector_egui::egui::{self, DragValue};
+
+use crate::history::doodad_commands::{DoodadChangeTracker, TransformCommand};
+use crate::history::{CommandGroup, EditorCommand, EditorHistory};
+
+use super::{compute_zone_bounds, ZoneBoundsQuery};
+
+/*
+
+The zone root transform is saved in the zone file and every doodad transform is local to it,
+so moving the zone root moves the whole zone.
+
+Baking and re-centering keep every doodad where it is in the world and only change how the
+transform is split between the zone root and its direct children.
+
+*/
+
+/// Sets the zone root transform and adjusts its direct children so nothing moves in the world.
+/// Recorded as one undo step.
+fn set_zone_origin(world: &mut World, zone_entity: Entity, new_root: Transform, label: &str) {
+    let Some(old_root) = world.get::<Transform>(zone_entity).copied() else {
+        return;
+    };
+
+    let children: Vec<Entity> = world
+        .get::<Children>(zone_entity)
+        .map(|children| children.iter().copied().collect())
+        .unwrap_or_default();
+
+    let root_change = new_root.compute_matrix().inverse() * old_root.compute_matrix();
+
+    let mut commands: Vec<Box<dyn EditorCommand>> = vec![Box::new(TransformCommand {
+        entity: zone_entity,
+        before: old_root,
+        after: new_root,
+    })];
+
+    for child in children {
+        let Some(mut child_transform) = world.get_mut::<Transform>(child) else {
+            continue;
+        };
+
+        let before = *child_transform;
+        let after = Transform::from_matrix(root_change * before.compute_matrix());
+        *child_transform = after;
+
+        DoodadChangeTracker::sync(world, child);
+
+        commands.push(Box::new(TransformCommand {
+            entity: child,
+            before,
+            after,
+        }));
+    }
+
+    if let Some(mut root_transform) = world.get_mut::<Transform>(zone_entity) {
+        *root_transform = new_root;
+    }
+
+    world.resource_mut::<EditorHistory>().push(CommandGroup {
+        label: label.to_string(),
+        commands,
+    });
+}
+
+/// Moves the zone root transform into its children, leaving the root at the identity.
+pub fn bake_zone_origin_into_children(world: &mut World, zone_entity: Entity) {
+    set_zone_origin(world, zone_entity, Transform::IDENTITY, "Bake zone origin");
+}
+
+/// Moves the zone root to the center of the zone bounds, keeping its rotation and scale.
+pub fn reset_zone_origin_to_bounds_center(world: &mut World, zone_entity: Entity) {
+    let Some(old_root) = world.get::<Transform>(zone_entity).copied() else {
+        return;
+    };
+
+    let mut bounds_state = SystemState::<ZoneBoundsQuery>::new(world);
+    let bounds = compute_zone_bounds(zone_entity, &bounds_state.get(world));
+
+    let Some(bounds) = bounds else {
+        return;
+    };
+
+    //bounds are in world space, the root translation is relative to the zone parent if it has one
+    let parent_matrix = world
+        .get::<Parent>(zone_entity)
+        .and_then(|parent| world.get::<GlobalTransform>(parent.get()))
+        .map(|global| global.compute_matrix())
+        .unwrap_or(Mat4::IDENTITY);
+
+    let center = (bounds.min + bounds.max) * 0.5;
+
+    let new_root = Transform {
+        translation: parent_matrix.inverse().transform_point3(center),
+        ..old_root
+    };
+
+    set_zone_origin(world, zone_entity, new_root, "Reset zone origin");
+}
+
+#[derive(Default)]
+pub struct ZoneOriginUiState {
+    //the rotation as it is being edited, so the degrees do not jump while dragging near gimbal lock
+    zone_entity: Option<Entity>,
+    rotation: Quat,
+    rotation_degrees: Vec3,
+    //root transform from before the current drag, pushed to the history once it ends
+    edit_start: Option<Transform>,
+}
+
+/// Edits the root transform of a zone, for the Zones window.
+pub(crate) fn zone_origin_ui(
+    world: &mut World,
+    state: &mut ZoneOriginUiState,
+    zone_entity: Entity,
+    ui: &mut egui::Ui,
+) {
+    let Some(mut transform) = world.get::<Transform>(zone_entity).copied() else {
+        return;
+    };
+
+    if state.zone_entity != Some(zone_entity) {
+        state.zone_entity = Some(zone_entity);
+        state.edit_start = None;
+    }
+
+    //only read the rotation back when something else changed it, like an undo
+    if state.rotation != transform.rotation {
+        let (yaw, pitch, roll) = transform.rotation.to_euler(EulerRot::YXZ);
+        state.rotation = transform.rotation;
+        state.rotation_degrees = Vec3::new(yaw, pitch, roll) * (180.0 / std::f32::consts::PI);
+    }
+
+    let before = transform;
+    let mut changed = false;
+    let mut editing = false;
+
+    let mut track = |response: egui::Response, changed: &mut bool| {
+        *changed |= response.changed();
+        editing |= response.dragged() || response.has_focus();
+    };
+
+    egui::Grid::new("zone_origin_grid").show(ui, |ui| {
+        ui.label("Translation");
+        track(ui.add(DragValue::new(&mut transform.translation.x).speed(0.1)), &mut changed);
+        track(ui.add(DragValue::new(&mut transform.translation.y).speed(0.1)), &mut changed);
+        track(ui.add(DragValue::new(&mut transform.translation.z).speed(0.1)), &mut changed);
+        ui.end_row();
+
+        ui.label("Rotation (yaw, pitch, roll)");
+        let mut rotation_changed = false;
+        let rotation_degrees = &mut state.rotation_degrees;
+        track(ui.add(DragValue::new(&mut rotation_degrees.x).suffix("°")), &mut rotation_changed);
+        track(ui.add(DragValue::new(&mut rotation_degrees.y).suffix("°")), &mut rotation_changed);
+        track(ui.add(DragValue::new(&mut rotation_degrees.z).suffix("°")), &mut rotation_changed);
+        if rotation_changed {
+            let radians = *rotation_degrees * (std::f32::consts::PI / 180.0);
+            transform.rotation = Quat::from_euler(EulerRot::YXZ, radians.x, radians.y, radians.z);
+            state.rotation = transform.rotation;
+            changed = true;
+        }
+        ui.end_row();
+
+        ui.label("Scale");
+        track(ui.add(DragValue::new(&mut transform.scale.x).speed(0.01)), &mut changed);
+        track(ui.add(DragValue::new(&mut transform.scale.y).speed(0.01)), &mut changed);
+        track(ui.add(DragValue::new(&mut transform.scale.z).speed(0.01)), &mut changed);
+        ui.end_row();
+    });
+
+    if changed {
+        if state.edit_start.is_none() {
+            state.edit_start = Some(before);
+        }
+        if let Some(mut zone_transform) = world.get_mut::<Transform>(zone_entity) {
+            *zone_transform = transform;
+        }
+    }
+
+    //one undo step per drag or typed value
+    if !editing {
+        if let Some(edit_start) = state.edit_start.take() {
+            if edit_start != transform {
+                world.resource_mut::<EditorHistory>().push(CommandGroup {
+                    label: "Move zone origin".to_string(),
+                    commands: vec![Box::new(TransformCommand {
+                        entity: zone_entity,
+                        before: edit_start,
+                        after: transform,
+                    })],
+                });
+            }
+        }
+    }
+
+    ui.horizontal(|ui| {
+        if ui
+            .button("Bake Into Children")
+            .on_hover_text("Keep every doodad in place and reset the zone origin")
+            .clicked()
+        {
+            bake_zone_origin_into_children(world, zone_entity);
+        }
+        if ui
+            .button("Reset Origin To Bounds Center")
+            .on_hover_text("Move the zone origin to the center of its doodads")
+            .clicked()
+        {
+            reset_zone_origin_to_bounds_center(world, zone_entity);
+        }
+    });
+}
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui::{self, RichText};
+
+use super::{zone_file_entities, ZoneEvent, ZoneFileQueries};
+
+/*
+
+Checks the custom props of a zone's doodads against the prop schemas in the doodad manifest
+when the zone is saved.  Depending on the project's invalid_props_on_save the zone is saved
+anyway or left unsaved, and either way the problems are listed in the Zones window.
+
+*/
+
+pub struct InvalidZoneProps {
+    pub zone_entity: Entity,
+    pub zone_name: String,
+    //the zone was not saved because of these
+    pub refused: bool,
+    pub messages: Vec<String>,
+}
+
+/// Zones whose last save found custom props that break their prop schema.
+#[derive(Resource, Default)]
+pub struct ZonePropValidationResource {
+    pub invalid_zones: Vec<InvalidZoneProps>,
+}
+
+impl ZonePropValidationResource {
+    pub fn record(&mut self, invalid_zone: InvalidZoneProps) {
+        self.clear(invalid_zone.zone_entity);
+        self.invalid_zones.push(invalid_zone);
+    }
+
+    pub fn clear(&mut self, zone_entity: Entity) {
+        self.invalid_zones.retain(|z| z.zone_entity != zone_entity);
+    }
+}
+
+/// One line per schema violation of the doodads in a zone, like `crate (3v2): "level" is required`.
+pub fn zone_prop_violations(zone_entity: Entity, queries: &ZoneFileQueries) -> Vec<String> {
+    let (entities, _) = zone_file_entities(zone_entity, queries);
+
+    let mut messages: Vec<String> = Vec::new();
+
+    for entity in entities {
+        let Ok(doodad) = queries.doodad_query.get(entity) else {
+            continue;
+        };
+        if doodad.definition.prop_schema.is_none() {
+            continue;
+        }
+        let Ok((name, _, custom_props)) = queries.zone_entity_query.get(entity) else {
+            continue;
+        };
+
+        let props = custom_props.map(|c| c.props.clone()).unwrap_or_default();
+
+        for violation in doodad.definition.validate_custom_props(&props) {
+            messages.push(format!("{} ({:?}): {}", name.as_str(), entity, violation));
+        }
+    }
+
+    messages
+}
+
+pub fn zone_prop_validation_ui(world: &mut World, ui: &mut egui::Ui) {
+    let invalid_zones: Vec<(Entity, String, bool, Vec<String>)> = world
+        .resource::<ZonePropValidationResource>()
+        .invalid_zones
+        .iter()
+        .map(|z| (z.zone_entity, z.zone_name.clone(), z.refused, z.messages.clone()))
+        .collect();
+
+    if invalid_zones.is_empty() {
+        return;
+    }
+
+    ui.separator();
+
+    for (zone_entity, zone_name, refused, messages) in invalid_zones.iter() {
+        let (heading, color) = match refused {
+            true => (
+                format!("{} was not saved, its custom props break the prop schema:", zone_name),
+                egui::Color32::RED,
+            ),
+            false => (
+                format!("{} was saved with custom props that break the prop schema:", zone_name),
+                egui::Color32::YELLOW,
+            ),
+        };
+        ui.label(RichText::new(heading).color(color));
+
+        egui::ScrollArea::vertical()
+            .id_source(("zone_prop_violations", *zone_entity))
+            .max_height(120.0)
+            .show(ui, |ui| {
+                for message in messages.iter() {
+                    ui.label(message);
+                }
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("Save Again").clicked() {
+                world.send_event::<ZoneEvent>(ZoneEvent::SaveZoneToFile(*zone_entity));
+            }
+            if ui.button("Dismiss").clicked() {
+                world
+                    .resource_mut::<ZonePropValidationResource>()
+                    .clear(*zone_entity);
+            }
+        });
+    }
+
+    ui.separator();
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// Persistent id of a placed doodad.  It is saved in the zone file so a game can refer to one
+/// specific doodad across saves, unlike the doodad name and position.  Clones get a new id.
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+#[serde(transparent)]
+pub struct DoodadId(pub u64);
+
+impl DoodadId {
+    pub fn new_random() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl fmt::Display for DoodadId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Lookup from doodad id to entity.
+#[derive(Resource, Default)]
+pub struct DoodadIdIndex {
+    entities: HashMap<DoodadId, Vec<Entity>>,
+    ids: HashMap<Entity, DoodadId>,
+}
+
+impl DoodadIdIndex {
+    /// The doodad with this id.  Zone instances spawn the same doodads more than once,
+    /// in that case this is the first one that was spawned.
+    pub fn get_entity(&self, id: DoodadId) -> Option<Entity> {
+        self.get_entities(id).first().copied()
+    }
+
+    /// Every doodad with this id, one per zone instance it is spawned in.
+    pub fn get_entities(&self, id: DoodadId) -> &[Entity] {
+        self.entities.get(&id).map(|e| e.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn get_id(&self, entity: Entity) -> Option<DoodadId> {
+        self.ids.get(&entity).copied()
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some(id) = self.ids.remove(&entity) else {
+            return;
+        };
+
+        if let Some(entities) = self.entities.get_mut(&id) {
+            entities.retain(|e| *e != entity);
+            if entities.is_empty() {
+                self.entities.remove(&id);
+            }
+        }
+    }
+
+    fn insert(&mut self, entity: Entity, id: DoodadId) {
+        self.remove(entity);
+        self.ids.insert(entity, id);
+        self.entities.entry(id).or_default().push(entity);
+    }
+}
+
+pub fn update_doodad_id_index(
+    mut doodad_id_index: ResMut<DoodadIdIndex>,
+    changed_id_query: Query<(Entity, &DoodadId), Changed<DoodadId>>,
+    mut removed_ids: RemovedComponents<DoodadId>,
+) {
+    for entity in removed_ids.read() {
+        doodad_id_index.remove(entity);
+    }
+
+    for (entity, doodad_id) in changed_id_query.iter() {
+        doodad_id_index.insert(entity, *doodad_id);
+    }
+}
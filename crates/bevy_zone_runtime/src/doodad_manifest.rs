@@ -0,0 +1,110 @@
+use bevy::utils::HashMap;
+use std::{
+    fs::File,
+    io::Read,
+    path::Path,
+};
+
+use bevy::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prop_schema::{validate_custom_props, CustomPropSchema, CustomPropViolation};
+use crate::zone_file::CustomPropsMap;
+
+pub type DoodadName = String;
+
+#[derive(Resource, Default)]
+pub struct DoodadManifestResource {
+    pub manifest: Option<Handle<DoodadManifest>>,
+ 
+}
+
+#[derive(Asset,  Clone, Debug, Serialize, Deserialize)]
+pub struct DoodadManifest {
+  //  pub doodad_tags: Vec<String>,
+    pub doodad_definitions: HashMap<DoodadName,DoodadDefinition>,
+}
+
+
+impl TypePath for DoodadManifest {
+    fn short_type_path() -> &'static str {
+        "doodadmanifest.ron"
+    }
+    fn type_path() -> &'static str {
+        "doodadmanifest.ron"
+    }
+}
+
+
+
+impl DoodadManifest {
+    pub fn get_doodad_definition_by_name(&self, name: &str) -> Option<DoodadDefinition> {
+     
+
+        return self.doodad_definitions.get(name).cloned();
+
+        
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RenderableType {
+    GltfModel(String), //the path
+    CubeShape(CubeShapeDefinition),
+    MagicFx(String),
+    LiquidPlane(String)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CubeShapeDefinition {
+    pub color: LinearRgba,
+    pub wireframe: bool 
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct DoodadDefinition {
+   // pub name: String,
+    pub model: RenderableType,
+    pub initial_custom_props: Option<CustomPropsMap>,
+    pub tags: Option<Vec<String>> ,
+    pub snap_dimensions: Option<Vec2>, 
+
+    //typed custom props this doodad expects, see prop_schema
+    #[serde(default)]
+    pub prop_schema: Option<Vec<CustomPropSchema>>,
+}
+
+impl DoodadDefinition {
+    /// Ways the props break the prop schema, empty when there is no schema.
+    pub fn validate_custom_props(&self, props: &CustomPropsMap) -> Vec<CustomPropViolation> {
+        match &self.prop_schema {
+            Some(prop_schema) => validate_custom_props(prop_schema, props),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl DoodadManifest {
+    pub fn load_from_file(file_path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = File::open(file_path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let data: Self = ron::de::from_str(&contents)?;
+        Ok(data)
+    }
+}
+
+/// A spawned doodad and the manifest definition it was spawned from.
+#[derive(Component, Debug)]
+pub struct DoodadComponent {
+    pub definition: DoodadDefinition,
+}
+
+impl DoodadComponent {
+    pub fn from_definition(definition: &DoodadDefinition) -> Self {
+        Self {
+            definition: definition.clone(),
+        }
+    }
+}
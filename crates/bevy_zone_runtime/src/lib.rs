@@ -0,0 +1,65 @@
+//! Zone files and doodad manifests written by the mesh terrain editor, without the editor.
+//!
+//! The editor uses this crate for its own file formats, so a game that depends on it reads
+//! exactly what the editor writes.
+//!
+//! ```ignore
+//! app.add_plugins(ZoneRuntimePlugin);
+//!
+//! let manifest = asset_server.load("doodad_manifest.doodadmanifest.ron");
+//! commands.insert_resource(DoodadManifestResource { manifest: Some(manifest) });
+//!
+//! commands
+//!     .spawn(SpatialBundle::default())
+//!     .insert(ZoneHandle(asset_server.load("zones/forest.zone.ron")));
+//! ```
+
+use bevy::prelude::*;
+use bevy_common_assets::ron::RonAssetPlugin;
+
+pub mod doodad_id;
+pub mod doodad_manifest;
+pub mod prop_schema;
+pub mod zone_file;
+pub mod zone_file_format;
+pub mod zone_loader;
+pub mod zone_migration;
+
+use doodad_id::{update_doodad_id_index, DoodadId, DoodadIdIndex};
+use doodad_manifest::{DoodadManifest, DoodadManifestResource};
+use zone_file::{CustomProp, CustomPropAssetPath, CustomPropsComponent, ZoneFile, ZoneMetadataComponent};
+use zone_loader::{respawn_changed_zones, spawn_zones, ZoneFileLoader};
+
+pub mod prelude {
+    pub use crate::doodad_id::{DoodadId, DoodadIdIndex};
+    pub use crate::doodad_manifest::{
+        DoodadComponent, DoodadDefinition, DoodadManifest, DoodadManifestResource, RenderableType,
+    };
+    pub use crate::prop_schema::{CustomPropSchema, CustomPropType, CustomPropViolation};
+    pub use crate::zone_file::{
+        custom_prop_doodad_references, CustomProp, CustomPropAssetPath, CustomPropsComponent, CustomPropsMap,
+        ZoneFile, ZoneMetadataComponent,
+    };
+    pub use crate::zone_loader::{SpawnedZone, ZoneHandle};
+    pub use crate::ZoneRuntimePlugin;
+}
+
+/// Loads `.zone.ron` / `.zone.bin` files and doodad manifests as assets and spawns zones.
+pub struct ZoneRuntimePlugin;
+
+impl Plugin for ZoneRuntimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<DoodadManifest>::new(&["doodadmanifest.ron"]))
+            .init_asset::<ZoneFile>()
+            .init_asset_loader::<ZoneFileLoader>()
+            .init_resource::<DoodadManifestResource>()
+            .init_resource::<DoodadIdIndex>()
+            .register_type::<DoodadId>()
+            .register_type::<CustomPropsComponent>()
+            .register_type::<CustomProp>()
+            .register_type::<CustomPropAssetPath>()
+            .register_type::<ZoneMetadataComponent>()
+            .add_systems(Update, update_doodad_id_index)
+            .add_systems(Update, (respawn_changed_zones, spawn_zones).chain());
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt;
+
+use bevy::color::LinearRgba;
+use serde::{Deserialize, Serialize};
+
+use crate::zone_file::{CustomProp, CustomPropsMap};
+
+/*
+
+A doodad definition can declare the custom props it expects:
+
+    prop_schema: Some([
+        (key: "loot_table", prop_type: String, required: true, choices: Some(["common", "boss"])),
+        (key: "level", prop_type: Integer, range: Some((1.0, 60.0)), description: Some("Spawn level")),
+    ]),
+
+The editor draws a widget per schema entry and flags props that break it, and zone saving warns
+about or refuses them.  A game can run the same validation when it loads a zone.
+
+*/
+
+/// The kind of value a custom prop holds, one per CustomProp variant.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CustomPropType {
+    Vec3,
+    String,
+    Float,
+    Integer,
+    Boolean,
+    Color,
+    Vec2,
+    AssetPath,
+    List,
+    Map,
+    DoodadReference,
+}
+
+impl CustomPropType {
+    /// None for EmptyProp, which has no type.
+    pub fn of(prop: &CustomProp) -> Option<Self> {
+        match prop {
+            CustomProp::Vec3(_) => Some(Self::Vec3),
+            CustomProp::String(_) => Some(Self::String),
+            CustomProp::Float(_) => Some(Self::Float),
+            CustomProp::Integer(_) => Some(Self::Integer),
+            CustomProp::Boolean(_) => Some(Self::Boolean),
+            CustomProp::Color(_) => Some(Self::Color),
+            CustomProp::Vec2(_) => Some(Self::Vec2),
+            CustomProp::AssetPath(_) => Some(Self::AssetPath),
+            CustomProp::List(_) => Some(Self::List),
+            CustomProp::Map(_) => Some(Self::Map),
+            CustomProp::DoodadReference(_) => Some(Self::DoodadReference),
+            CustomProp::EmptyProp => None,
+        }
+    }
+
+    /// The value a newly added prop of this type starts with.  Doodad references start out
+    /// empty, since there is no doodad to point at yet.
+    pub fn default_value(&self) -> CustomProp {
+        match self {
+            Self::Vec3 => CustomProp::Vec3(Default::default()),
+            Self::String => CustomProp::String(String::new()),
+            Self::Float => CustomProp::Float(0.0),
+            Self::Integer => CustomProp::Integer(0),
+            Self::Boolean => CustomProp::Boolean(false),
+            Self::Color => CustomProp::Color(LinearRgba::WHITE),
+            Self::Vec2 => CustomProp::Vec2(Default::default()),
+            Self::AssetPath => CustomProp::AssetPath(Default::default()),
+            Self::List => CustomProp::List(Vec::new()),
+            Self::Map => CustomProp::Map(Default::default()),
+            Self::DoodadReference => CustomProp::EmptyProp,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CustomPropSchema {
+    pub key: String,
+    pub prop_type: CustomPropType,
+
+    #[serde(default)]
+    pub required: bool,
+
+    //inclusive (min, max) for Float and Integer props
+    #[serde(default)]
+    pub range: Option<(f32, f32)>,
+
+    //allowed values of a String prop
+    #[serde(default)]
+    pub choices: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl CustomPropSchema {
+    /// A valid value to add when the prop is missing: the first choice or the bottom of the range.
+    pub fn initial_value(&self) -> CustomProp {
+        if let Some(first_choice) = self.choices.as_ref().and_then(|choices| choices.first()) {
+            return CustomProp::String(first_choice.clone());
+        }
+
+        match (self.prop_type, self.range) {
+            (CustomPropType::Float, Some((min, _))) => CustomProp::Float(min),
+            (CustomPropType::Integer, Some((min, _))) => CustomProp::Integer(min.ceil() as i32),
+            (prop_type, _) => prop_type.default_value(),
+        }
+    }
+
+    /// Whether the prop is unset, which only an optional doodad reference may be.
+    pub fn is_unset_reference(&self, prop: &CustomProp) -> bool {
+        self.prop_type == CustomPropType::DoodadReference && *prop == CustomProp::EmptyProp
+    }
+
+    fn validate(&self, prop: &CustomProp) -> Option<CustomPropViolation> {
+        if self.is_unset_reference(prop) {
+            return match self.required {
+                true => Some(CustomPropViolation::MissingRequired {
+                    key: self.key.clone(),
+                }),
+                false => None,
+            };
+        }
+
+        let found = CustomPropType::of(prop);
+        if found != Some(self.prop_type) {
+            return Some(CustomPropViolation::WrongType {
+                key: self.key.clone(),
+                expected: self.prop_type,
+                found,
+            });
+        }
+
+        let number = match prop {
+            CustomProp::Float(value) => Some(*value),
+            CustomProp::Integer(value) => Some(*value as f32),
+            _ => None,
+        };
+        if let (Some(value), Some((min, max))) = (number, self.range) {
+            if value < min || value > max {
+                return Some(CustomPropViolation::OutOfRange {
+                    key: self.key.clone(),
+                    value,
+                    min,
+                    max,
+                });
+            }
+        }
+
+        if let (CustomProp::String(value), Some(choices)) = (prop, &self.choices) {
+            if !choices.contains(value) {
+                return Some(CustomPropViolation::NotAChoice {
+                    key: self.key.clone(),
+                    value: value.clone(),
+                    choices: choices.clone(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CustomPropViolation {
+    MissingRequired {
+        key: String,
+    },
+    WrongType {
+        key: String,
+        expected: CustomPropType,
+        found: Option<CustomPropType>,
+    },
+    OutOfRange {
+        key: String,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+    NotAChoice {
+        key: String,
+        value: String,
+        choices: Vec<String>,
+    },
+    //usually a misspelled key
+    UnknownKey {
+        key: String,
+    },
+}
+
+impl CustomPropViolation {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::MissingRequired { key }
+            | Self::WrongType { key, .. }
+            | Self::OutOfRange { key, .. }
+            | Self::NotAChoice { key, .. }
+            | Self::UnknownKey { key } => key,
+        }
+    }
+}
+
+impl fmt::Display for CustomPropViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingRequired { key } => write!(f, "{:?} is required", key),
+            Self::WrongType { key, expected, found } => match found {
+                Some(found) => write!(f, "{:?} should be {:?}, not {:?}", key, expected, found),
+                None => write!(f, "{:?} should be {:?}, it is empty", key, expected),
+            },
+            Self::OutOfRange { key, value, min, max } => {
+                write!(f, "{:?} is {}, outside {} to {}", key, value, min, max)
+            }
+            Self::NotAChoice { key, value, choices } => {
+                write!(f, "{:?} is {:?}, expected one of {:?}", key, value, choices)
+            }
+            Self::UnknownKey { key } => write!(f, "{:?} is not in the schema", key),
+        }
+    }
+}
+
+/// Every way the props break the schema, sorted by key.
+pub fn validate_custom_props(
+    schema: &[CustomPropSchema],
+    props: &CustomPropsMap,
+) -> Vec<CustomPropViolation> {
+    let mut violations: Vec<CustomPropViolation> = Vec::new();
+
+    for prop_schema in schema.iter() {
+        match props.get(&prop_schema.key) {
+            Some(prop) => violations.extend(prop_schema.validate(prop)),
+            None if prop_schema.required => violations.push(CustomPropViolation::MissingRequired {
+                key: prop_schema.key.clone(),
+            }),
+            None => {}
+        }
+    }
+
+    for key in props.keys() {
+        if !schema.iter().any(|prop_schema| &prop_schema.key == key) {
+            violations.push(CustomPropViolation::UnknownKey { key: key.clone() });
+        }
+    }
+
+    violations.sort_by(|a, b| a.key().cmp(b.key()));
+
+    violations
+}
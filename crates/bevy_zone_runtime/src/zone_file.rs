@@ -0,0 +1,524 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashMap};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::zone_migration::CURRENT_ZONE_FORMAT_VERSION;
+use crate::doodad_id::DoodadId;
+
+//an asset so games can load zones through the asset server, see zone_loader
+#[derive(Asset, TypePath, Serialize, Deserialize)]
+pub struct ZoneFile {
+    //files from before versioning have no version and load as 0
+    #[serde(default)]
+    pub format_version: u32,
+
+    //files from before format v4 have no metadata
+    #[serde(default)]
+    pub metadata: ZoneMetadata,
+
+    //transform of the zone root, entity transforms are local to it. files from before format v5 have none
+    #[serde(default)]
+    pub root_transform: TransformSimple,
+
+    pub entities: Vec<ZoneEntity>, 
+
+    //other zone files spawned inside this zone, files from before format v6 have none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub zone_instances: Vec<ZoneInstance>,
+}
+
+impl ZoneFile {
+    //entities must list parents before their children, like DescendantIter does
+    pub fn new(
+        metadata: ZoneMetadata,
+        root_transform: Transform,
+        entities: Vec<Entity>,
+        zone_instances: Vec<ZoneInstance>,
+        zone_entity_query: &Query<(&Name, &Transform, Option<&CustomPropsComponent>)>,
+        parent_query: &Query<&Parent>,
+        doodad_id_query: &Query<&DoodadId>,
+    ) -> Self {
+        let mut zone_entities: Vec<ZoneEntity> = Vec::new();
+        let mut saved_indices: HashMap<Entity, usize> = HashMap::new();
+
+        for entity in entities {
+            if let Some(mut zone_entity) = ZoneEntity::from_entity(entity, &zone_entity_query) {
+                //anything not parented to another saved entity is a direct child of the zone
+                zone_entity.parent = parent_query
+                    .get(entity)
+                    .ok()
+                    .and_then(|parent| saved_indices.get(&parent.get()).copied());
+                zone_entity.id = doodad_id_query.get(entity).ok().copied();
+
+                saved_indices.insert(entity, zone_entities.len());
+                zone_entities.push(zone_entity);
+            }
+        }
+
+        Self {
+            format_version: CURRENT_ZONE_FORMAT_VERSION,
+            metadata,
+            root_transform: root_transform.into(),
+            entities: sort_zone_entities(zone_entities),
+            zone_instances,
+        }
+    }
+
+    /// Multi-line RON with a fixed layout, so saving an unchanged zone gives an identical file.
+    pub fn to_ron_string(&self) -> Result<String, ron::Error> {
+        let pretty_config = PrettyConfig::new()
+            //transforms and vectors stay on one line each
+            .depth_limit(4)
+            .new_line("\n".to_string())
+            .indentor("    ".to_string());
+
+        ron::ser::to_string_pretty(self, pretty_config)
+    }
+
+    pub fn get_root_transform(&self) -> Transform {
+        self.root_transform.clone().into()
+    }
+}
+
+/*
+Saved files should only change where the zone changed.  Children order in the hierarchy and
+HashMap order are not stable, so entities are sorted by id (parents still come before their
+children) and custom props by key.  Only the informational euler angles are rounded, so noise
+from recomputing them from the quaternion does not show up as a change.  Everything that is read
+back is written at full precision.
+*/
+
+const SAVED_FLOAT_PRECISION: f32 = 1_000_000.0;
+
+fn round_saved_float(value: f32) -> f32 {
+    let rounded = (value * SAVED_FLOAT_PRECISION).round() / SAVED_FLOAT_PRECISION;
+
+    //large values do not fit the extra digits, and -0.0 would be written differently from 0.0
+    match rounded.is_finite() {
+        true if rounded == 0.0 => 0.0,
+        true => rounded,
+        false => value,
+    }
+}
+
+fn serialize_rounded_vec3<S: Serializer>(value: &Vec3, serializer: S) -> Result<S::Ok, S::Error> {
+    Vec3::new(
+        round_saved_float(value.x),
+        round_saved_float(value.y),
+        round_saved_float(value.z),
+    )
+    .serialize(serializer)
+}
+
+fn serialize_sorted_props<S: Serializer>(
+    props: &CustomPropsMap,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    props.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+fn serialize_sorted_optional_props<S: Serializer>(
+    props: &Option<CustomPropsMap>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    props
+        .as_ref()
+        .map(|props| props.iter().collect::<BTreeMap<_, _>>())
+        .serialize(serializer)
+}
+
+//depth first with siblings ordered by id, parent indices are remapped to the new order
+fn sort_zone_entities(zone_entities: Vec<ZoneEntity>) -> Vec<ZoneEntity> {
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); zone_entities.len()];
+    let mut roots: Vec<usize> = Vec::new();
+
+    for (index, zone_entity) in zone_entities.iter().enumerate() {
+        match zone_entity.parent {
+            Some(parent_index) => children[parent_index].push(index),
+            None => roots.push(index),
+        }
+    }
+
+    let sort_key = |index: &usize| {
+        let zone_entity = &zone_entities[*index];
+        (zone_entity.id.map(|id| id.0), zone_entity.name.clone(), *index)
+    };
+
+    roots.sort_by_key(sort_key);
+    for siblings in children.iter_mut() {
+        siblings.sort_by_key(sort_key);
+    }
+
+    let mut order: Vec<usize> = Vec::with_capacity(zone_entities.len());
+    let mut stack: Vec<usize> = roots.into_iter().rev().collect();
+    while let Some(index) = stack.pop() {
+        order.push(index);
+        stack.extend(children[index].iter().rev());
+    }
+
+    let mut new_indices: Vec<usize> = vec![0; zone_entities.len()];
+    for (new_index, old_index) in order.iter().enumerate() {
+        new_indices[*old_index] = new_index;
+    }
+
+    let mut zone_entities: Vec<Option<ZoneEntity>> = zone_entities.into_iter().map(Some).collect();
+
+    order
+        .into_iter()
+        .filter_map(|old_index| zone_entities[old_index].take())
+        .map(|mut zone_entity| {
+            zone_entity.parent = zone_entity.parent.map(|parent_index| new_indices[parent_index]);
+            zone_entity
+        })
+        .collect()
+}
+
+/// Zone-wide settings that a game can read from the zone file, like music or ambience.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Reflect)]
+#[reflect(Default)]
+#[serde(default)]
+pub struct ZoneMetadata {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub display_name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    //world space bounds of everything in the zone, recomputed every time the zone is saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<ZoneBounds>,
+
+    #[serde(skip_serializing_if = "HashMap::is_empty", serialize_with = "serialize_sorted_props")]
+    pub properties: CustomPropsMap,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct ZoneBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl ZoneBounds {
+    pub fn from_point(point: Vec3) -> Self {
+        Self { min: point, max: point }
+    }
+
+    pub fn extend(&mut self, point: Vec3) {
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+}
+
+//on the zone entity, reflect makes the metadata editable in the inspector
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct ZoneMetadataComponent {
+    pub metadata: ZoneMetadata,
+}
+
+//reflect makes this show up in the inspector
+#[derive(Component, Reflect,Default)]
+#[reflect(Component)]
+pub struct CustomPropsComponent {
+    pub props: CustomPropsMap,
+}
+
+impl CustomPropsComponent {
+    pub fn set_custom_props_if_empty(&mut self, new_props: &CustomPropsMap){
+
+        for (key,val) in new_props.iter(){
+
+            if self.props.get( key ).is_none(){
+                  self.props.insert(key.to_string(), val.clone());
+            }
+          
+        }
+
+    }
+
+
+    pub fn set_custom_props(&mut self, new_props: &CustomPropsMap){
+
+        for (key,val) in new_props.iter(){
+
+
+            self.props.insert(key.to_string(), val.clone());
+        }
+
+    }
+}
+
+pub type CustomPropsMap = HashMap<String, CustomProp>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect, Default)]
+#[reflect(Serialize, Deserialize,Default)]  //need to reflect default or else cant use + in editor 
+#[reflect(no_field_bounds)]  //lists and maps hold more props, field bounds would recurse forever
+pub enum CustomProp {
+    Vec3(Vec3),
+    String(String),
+    Float(f32),
+    Integer(i32), 
+    Boolean(bool),
+    #[default]
+    EmptyProp,
+
+    //binary zone files store the variant index, so new variants only ever go at the end
+    Color(LinearRgba),
+    Vec2(Vec2),
+    AssetPath(CustomPropAssetPath),
+    List(Vec<CustomProp>),
+    Map(#[serde(serialize_with = "serialize_sorted_props")] CustomPropsMap),
+    //another placed doodad, like the lever a door listens to
+    DoodadReference(DoodadId),
+}
+
+impl CustomProp {
+    fn collect_doodad_references(&self, key: String, references: &mut Vec<(String, DoodadId)>) {
+        match self {
+            Self::DoodadReference(id) => references.push((key, *id)),
+            Self::List(props) => {
+                for (index, prop) in props.iter().enumerate() {
+                    prop.collect_doodad_references(format!("{}[{}]", key, index), references);
+                }
+            }
+            Self::Map(props) => {
+                for (inner_key, prop) in props.iter() {
+                    prop.collect_doodad_references(format!("{}.{}", key, inner_key), references);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn remap_doodad_references(&mut self, ids: &HashMap<DoodadId, DoodadId>) {
+        match self {
+            Self::DoodadReference(id) => {
+                if let Some(new_id) = ids.get(id) {
+                    *id = *new_id;
+                }
+            }
+            Self::List(props) => {
+                for prop in props.iter_mut() {
+                    prop.remap_doodad_references(ids);
+                }
+            }
+            Self::Map(props) => {
+                for prop in props.values_mut() {
+                    prop.remap_doodad_references(ids);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Points every doodad reference in the props, including those inside lists and maps, at the
+/// new id of its target.  References to ids that are not in the map are left alone.
+pub fn remap_custom_prop_doodad_references(props: &mut CustomPropsMap, ids: &HashMap<DoodadId, DoodadId>) {
+    for prop in props.values_mut() {
+        prop.remap_doodad_references(ids);
+    }
+}
+
+/// Every doodad reference in the props, sorted by key.  References inside lists and maps are
+/// keyed like `waypoints[2]` and `loot.chest`.
+pub fn custom_prop_doodad_references(props: &CustomPropsMap) -> Vec<(String, DoodadId)> {
+    let mut references: Vec<(String, DoodadId)> = Vec::new();
+
+    for (key, prop) in props.iter() {
+        prop.collect_doodad_references(key.clone(), &mut references);
+    }
+
+    references.sort_by(|a, b| a.0.cmp(&b.0));
+    references
+}
+
+/// A path relative to the asset folder, written as a plain string.  Its own type so the editor
+/// can show a file picker for it instead of a text field.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Default)]
+#[serde(transparent)]
+pub struct CustomPropAssetPath(pub String);
+
+/// Another zone file spawned inside a zone.  Its entities are loaded from its own file every
+/// time, so edits to the source zone show up in every instance.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ZoneInstance {
+    //file stem of the referenced zone
+    pub zone_name: String,
+
+    //offset from the zone root, replaces the root transform of the referenced zone
+    pub transform: TransformSimple,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ZoneEntity {
+    //files from before format v7 have no ids, the migration gives every entity one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<DoodadId>,
+
+    pub name: String,
+
+    pub transform: TransformSimple,
+
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_sorted_optional_props")]
+    pub custom_props: Option<CustomPropsMap>,
+
+    //index of the parent entity in the zone file, none means parented directly to the zone.
+    //the transform is local to that parent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+}
+
+impl ZoneEntity {
+    pub fn get_position(&self) -> Vec3 {
+        self.transform.translation
+    }
+
+    pub fn get_rotation_euler(&self) -> Vec3 {
+        self.transform.rotation
+    }
+
+    pub fn get_rotation(&self) -> Quat {
+        self.transform.rotation_quat
+    }
+
+    pub fn get_scale(&self) -> Vec3 {
+        self.transform.scale
+    }
+
+    pub fn get_custom_props(&self) -> &Option<CustomPropsMap> {
+        &self.custom_props
+    }
+
+    pub fn get_parent_index(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn get_id(&self) -> Option<DoodadId> {
+        self.id
+    }
+
+    fn from_entity(
+        entity: Entity,
+        zone_entity_query: &Query<(&Name, &Transform, Option<&CustomPropsComponent>)>,
+    ) -> Option<Self> {
+        if let Some((name, xform, custom_props_component)) = zone_entity_query.get(entity).ok() {
+            let custom_props = custom_props_component.and_then(|comp| Some(comp.props.clone()));
+
+            return Some(Self {
+                id: None,
+                name: name.as_str().to_string(),
+                transform: xform.clone().into(),
+                custom_props,
+                parent: None,
+            });
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransformSimple {
+    pub translation: Vec3,
+
+    //euler (yaw, pitch, roll), only kept so the files stay readable. older files (format < 2) only have this
+    #[serde(default, serialize_with = "serialize_rounded_vec3")]
+    pub rotation: Vec3,
+
+    //the actual rotation, euler angles do not round trip exactly
+    #[serde(default = "default_rotation_quat")]
+    pub rotation_quat: Quat,
+
+    pub scale: Vec3,
+}
+
+fn default_rotation_quat() -> Quat {
+    Quat::IDENTITY
+}
+
+impl TransformSimple {
+    pub fn rotation_from_euler(euler: Vec3) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, euler.x, euler.y, euler.z)
+    }
+}
+
+impl Default for TransformSimple {
+    fn default() -> Self {
+        Transform::IDENTITY.into()
+    }
+}
+
+impl From<TransformSimple> for Transform {
+    fn from(transform: TransformSimple) -> Self {
+        Transform {
+            translation: transform.translation,
+            rotation: transform.rotation_quat,
+            scale: transform.scale,
+        }
+    }
+}
+
+impl From<Transform> for TransformSimple {
+    fn from(transform: Transform) -> Self {
+        // Extract translation directly
+        let translation = transform.translation;
+
+        // Convert quaternion to Euler angles (in radians)
+        let ( yaw, pitch,  roll) = transform.rotation.to_euler(EulerRot::YXZ);
+
+        // Extract scale directly
+        let scale = transform.scale;
+
+        // Create and return a new instance of TransformSimple
+        TransformSimple {
+            translation,
+            rotation: Vec3::new(yaw, pitch, roll ), 
+            rotation_quat: transform.rotation,
+            scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaps_nested_doodad_references() {
+        let ids: HashMap<DoodadId, DoodadId> = [(DoodadId(1), DoodadId(10)), (DoodadId(2), DoodadId(20))]
+            .into_iter()
+            .collect();
+
+        let mut loot = CustomPropsMap::default();
+        loot.insert("chest".to_string(), CustomProp::DoodadReference(DoodadId(2)));
+
+        let mut props = CustomPropsMap::default();
+        props.insert("lever".to_string(), CustomProp::DoodadReference(DoodadId(1)));
+        props.insert(
+            "waypoints".to_string(),
+            CustomProp::List(vec![CustomProp::DoodadReference(DoodadId(2)), CustomProp::Integer(2)]),
+        );
+        props.insert("loot".to_string(), CustomProp::Map(loot));
+        //points outside of the remapped doodads
+        props.insert("other".to_string(), CustomProp::DoodadReference(DoodadId(3)));
+
+        remap_custom_prop_doodad_references(&mut props, &ids);
+
+        assert_eq!(
+            custom_prop_doodad_references(&props),
+            vec![
+                ("lever".to_string(), DoodadId(10)),
+                ("loot.chest".to_string(), DoodadId(20)),
+                ("other".to_string(), DoodadId(3)),
+                ("waypoints[0]".to_string(), DoodadId(20)),
+            ]
+        );
+    }
+}
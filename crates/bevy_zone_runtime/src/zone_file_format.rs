@@ -0,0 +1,374 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::doodad_id::DoodadId;
+
+use crate::zone_file::{
+    CustomPropsMap, TransformSimple, ZoneBounds, ZoneEntity, ZoneFile, ZoneInstance, ZoneMetadata,
+};
+use crate::zone_migration::{load_and_migrate_zone_file, CURRENT_ZONE_FORMAT_VERSION};
+
+/*
+
+Zone files are RON (`.zone.ron`) for authoring, or a compact binary encoding (`.zone.bin`)
+that is much faster to parse for zones with thousands of doodads.
+
+The binary file is a small header followed by the zone encoded with bincode.  bincode is not
+self describing, so it cannot skip fields the way the RON layout does.  The zone is copied into
+the Binary* structs below, which always write every field.  Binary files are only ever
+written in the current format version, older ones have to be converted again from RON.
+
+*/
+
+pub const RON_ZONE_EXTENSION: &str = "zone.ron";
+pub const BINARY_ZONE_EXTENSION: &str = "zone.bin";
+
+const BINARY_ZONE_MAGIC: &[u8; 4] = b"ZONB";
+
+type ZoneFormatResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZoneFileFormat {
+    Ron,
+    Binary,
+}
+
+impl ZoneFileFormat {
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let is_binary = path
+            .as_ref()
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| name.ends_with(BINARY_ZONE_EXTENSION));
+
+        match is_binary {
+            true => Self::Binary,
+            false => Self::Ron,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Ron => RON_ZONE_EXTENSION,
+            Self::Binary => BINARY_ZONE_EXTENSION,
+        }
+    }
+
+    pub fn other(&self) -> Self {
+        match self {
+            Self::Ron => Self::Binary,
+            Self::Binary => Self::Ron,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ron => "RON",
+            Self::Binary => "Binary",
+        }
+    }
+}
+
+/// Reads a zone file in either format.  Returns the version it was upgraded from, if it was upgraded.
+pub fn read_zone_file(path: impl AsRef<Path>) -> ZoneFormatResult<(ZoneFile, Option<u32>)> {
+    let path = path.as_ref();
+
+    decode_zone_file(
+        &fs::read(path)?,
+        ZoneFileFormat::from_path(path),
+        &zone_name_from_path(path).unwrap_or_default(),
+    )
+}
+
+/// Parses the contents of a zone file.  `zone_name` is the file stem, older files derive ids from it.
+/// Returns the version it was upgraded from, if it was upgraded.
+pub fn decode_zone_file(
+    bytes: &[u8],
+    format: ZoneFileFormat,
+    zone_name: &str,
+) -> ZoneFormatResult<(ZoneFile, Option<u32>)> {
+    match format {
+        ZoneFileFormat::Ron => load_and_migrate_zone_file(std::str::from_utf8(bytes)?, zone_name),
+        ZoneFileFormat::Binary => Ok((decode_binary_zone_file(bytes)?, None)),
+    }
+}
+
+/// Writes a zone file in the format its extension asks for.
+pub fn write_zone_file(path: impl AsRef<Path>, zone_file: &ZoneFile) -> ZoneFormatResult<()> {
+    let path = path.as_ref();
+
+    match ZoneFileFormat::from_path(path) {
+        ZoneFileFormat::Ron => fs::write(path, zone_file.to_ron_string()?)?,
+        ZoneFileFormat::Binary => fs::write(path, encode_binary_zone_file(zone_file)?)?,
+    };
+
+    Ok(())
+}
+
+//the file name without the format extension, keeping the dot before it
+fn zone_file_stem_with_dot(path: &Path) -> Option<&str> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| {
+            name.strip_suffix(RON_ZONE_EXTENSION)
+                .or_else(|| name.strip_suffix(BINARY_ZONE_EXTENSION))
+        })
+}
+
+/// The zone name of a zone file path, its file name without the format extension.
+pub fn zone_name_from_path(path: impl AsRef<Path>) -> Option<String> {
+    zone_file_stem_with_dot(path.as_ref()).map(|stem| stem.trim_end_matches('.').to_string())
+}
+
+/// The path of the same zone in another format, in the same folder.
+pub fn zone_file_path_as(path: impl AsRef<Path>, format: ZoneFileFormat) -> ZoneFormatResult<PathBuf> {
+    let path = path.as_ref();
+
+    let stem = zone_file_stem_with_dot(path).ok_or_else(|| format!("{:?} is not a zone file", path))?;
+
+    Ok(path.with_file_name(format!("{}{}", stem, format.extension())))
+}
+
+/// Reads a zone file and writes it to `destination`, in the format of the destination extension.
+pub fn convert_zone_file(
+    source: impl AsRef<Path>,
+    destination: impl AsRef<Path>,
+) -> ZoneFormatResult<()> {
+    let (zone_file, _) = read_zone_file(source)?;
+    write_zone_file(destination, &zone_file)
+}
+
+pub fn encode_binary_zone_file(zone_file: &ZoneFile) -> ZoneFormatResult<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(BINARY_ZONE_MAGIC);
+    bytes.extend_from_slice(&CURRENT_ZONE_FORMAT_VERSION.to_le_bytes());
+
+    bincode::serialize_into(&mut bytes, &BinaryZoneFile::from(zone_file))?;
+
+    Ok(bytes)
+}
+
+pub fn decode_binary_zone_file(bytes: &[u8]) -> ZoneFormatResult<ZoneFile> {
+    if bytes.len() < 8 || &bytes[0..4] != BINARY_ZONE_MAGIC {
+        return Err("not a binary zone file".into());
+    }
+
+    let file_version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if file_version != CURRENT_ZONE_FORMAT_VERSION {
+        return Err(format!(
+            "binary zone format version {} does not match this version ({}), convert it again from RON",
+            file_version, CURRENT_ZONE_FORMAT_VERSION
+        )
+        .into());
+    }
+
+    let binary_zone_file: BinaryZoneFile = bincode::deserialize(&bytes[8..])?;
+
+    Ok(binary_zone_file.into())
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryZoneFile {
+    metadata: BinaryZoneMetadata,
+    root_transform: TransformSimple,
+    entities: Vec<BinaryZoneEntity>,
+    zone_instances: Vec<ZoneInstance>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryZoneMetadata {
+    display_name: String,
+    description: String,
+    author: String,
+    tags: Vec<String>,
+    bounds: Option<ZoneBounds>,
+    properties: CustomPropsMap,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryZoneEntity {
+    id: Option<DoodadId>,
+    name: String,
+    transform: TransformSimple,
+    custom_props: Option<CustomPropsMap>,
+    parent: Option<usize>,
+}
+
+impl From<&ZoneFile> for BinaryZoneFile {
+    fn from(zone_file: &ZoneFile) -> Self {
+        let metadata = &zone_file.metadata;
+
+        Self {
+            metadata: BinaryZoneMetadata {
+                display_name: metadata.display_name.clone(),
+                description: metadata.description.clone(),
+                author: metadata.author.clone(),
+                tags: metadata.tags.clone(),
+                bounds: metadata.bounds,
+                properties: metadata.properties.clone(),
+            },
+            root_transform: zone_file.root_transform.clone(),
+            entities: zone_file
+                .entities
+                .iter()
+                .map(|zone_entity| BinaryZoneEntity {
+                    id: zone_entity.id,
+                    name: zone_entity.name.clone(),
+                    transform: zone_entity.transform.clone(),
+                    custom_props: zone_entity.custom_props.clone(),
+                    parent: zone_entity.parent,
+                })
+                .collect(),
+            zone_instances: zone_file.zone_instances.clone(),
+        }
+    }
+}
+
+impl From<BinaryZoneFile> for ZoneFile {
+    fn from(binary_zone_file: BinaryZoneFile) -> Self {
+        let metadata = binary_zone_file.metadata;
+
+        Self {
+            format_version: CURRENT_ZONE_FORMAT_VERSION,
+            metadata: ZoneMetadata {
+                display_name: metadata.display_name,
+                description: metadata.description,
+                author: metadata.author,
+                tags: metadata.tags,
+                bounds: metadata.bounds,
+                properties: metadata.properties,
+            },
+            root_transform: binary_zone_file.root_transform,
+            entities: binary_zone_file
+                .entities
+                .into_iter()
+                .map(|zone_entity| ZoneEntity {
+                    id: zone_entity.id,
+                    name: zone_entity.name,
+                    transform: zone_entity.transform,
+                    custom_props: zone_entity.custom_props,
+                    parent: zone_entity.parent,
+                })
+                .collect(),
+            zone_instances: binary_zone_file.zone_instances,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use bevy::utils::HashMap;
+
+    use super::*;
+    use crate::zone_file::{CustomProp, CustomPropAssetPath};
+
+    fn test_zone_file() -> ZoneFile {
+        let mut loot = CustomPropsMap::default();
+        loot.insert("gold".to_string(), CustomProp::Integer(25));
+        loot.insert("chest".to_string(), CustomProp::DoodadReference(DoodadId(7)));
+
+        let mut props = CustomPropsMap::default();
+        props.insert("offset".to_string(), CustomProp::Vec3(Vec3::new(1.0, 2.0, 3.0)));
+        props.insert("label".to_string(), CustomProp::String("door".to_string()));
+        props.insert("speed".to_string(), CustomProp::Float(0.5));
+        props.insert("locked".to_string(), CustomProp::Boolean(true));
+        props.insert("unset".to_string(), CustomProp::EmptyProp);
+        props.insert("tint".to_string(), CustomProp::Color(LinearRgba::new(0.1, 0.2, 0.3, 1.0)));
+        props.insert("uv".to_string(), CustomProp::Vec2(Vec2::new(0.25, 0.75)));
+        props.insert(
+            "sound".to_string(),
+            CustomProp::AssetPath(CustomPropAssetPath("sounds/door.ogg".to_string())),
+        );
+        props.insert(
+            "waypoints".to_string(),
+            CustomProp::List(vec![
+                CustomProp::DoodadReference(DoodadId(3)),
+                CustomProp::List(vec![CustomProp::Integer(1)]),
+            ]),
+        );
+        props.insert("loot".to_string(), CustomProp::Map(loot));
+        props.insert("lever".to_string(), CustomProp::DoodadReference(DoodadId(u64::MAX)));
+
+        ZoneFile {
+            format_version: CURRENT_ZONE_FORMAT_VERSION,
+            metadata: ZoneMetadata {
+                display_name: "Forest".to_string(),
+                tags: vec!["outdoor".to_string()],
+                bounds: Some(ZoneBounds {
+                    min: Vec3::splat(-4.0),
+                    max: Vec3::splat(4.0),
+                }),
+                properties: HashMap::from([("music".to_string(), CustomProp::String("calm".to_string()))]),
+                ..default()
+            },
+            root_transform: Transform::from_xyz(10.0, 0.0, -5.0).into(),
+            entities: vec![
+                ZoneEntity {
+                    id: Some(DoodadId(1)),
+                    name: "door".to_string(),
+                    transform: Transform::from_rotation(Quat::from_rotation_y(0.5)).into(),
+                    custom_props: Some(props),
+                    parent: None,
+                },
+                ZoneEntity {
+                    id: Some(DoodadId(2)),
+                    name: "handle".to_string(),
+                    transform: Transform::from_scale(Vec3::splat(2.0)).into(),
+                    custom_props: None,
+                    parent: Some(0),
+                },
+            ],
+            zone_instances: vec![ZoneInstance {
+                zone_name: "house".to_string(),
+                transform: Transform::from_xyz(0.0, 1.0, 0.0).into(),
+            }],
+        }
+    }
+
+    #[test]
+    fn binary_round_trip_matches_ron() {
+        let ron = test_zone_file().to_ron_string().unwrap();
+        let (zone_file, upgraded_from) =
+            decode_zone_file(ron.as_bytes(), ZoneFileFormat::Ron, "forest").unwrap();
+        assert_eq!(upgraded_from, None);
+
+        let bytes = encode_binary_zone_file(&zone_file).unwrap();
+        let decoded = decode_binary_zone_file(&bytes).unwrap();
+
+        assert_eq!(decoded.to_ron_string().unwrap(), ron);
+    }
+
+    //binary files store the variant index, reordering the variants breaks every existing file
+    #[test]
+    fn custom_prop_variant_indices_are_stable() {
+        let variant_index = |prop: CustomProp| {
+            let bytes = bincode::serialize(&prop).unwrap();
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+
+        assert_eq!(variant_index(CustomProp::Vec3(Vec3::ZERO)), 0);
+        assert_eq!(variant_index(CustomProp::String(String::new())), 1);
+        assert_eq!(variant_index(CustomProp::Float(0.0)), 2);
+        assert_eq!(variant_index(CustomProp::Integer(0)), 3);
+        assert_eq!(variant_index(CustomProp::Boolean(false)), 4);
+        assert_eq!(variant_index(CustomProp::EmptyProp), 5);
+        assert_eq!(variant_index(CustomProp::Color(LinearRgba::BLACK)), 6);
+        assert_eq!(variant_index(CustomProp::Vec2(Vec2::ZERO)), 7);
+        assert_eq!(variant_index(CustomProp::AssetPath(CustomPropAssetPath::default())), 8);
+        assert_eq!(variant_index(CustomProp::List(Vec::new())), 9);
+        assert_eq!(variant_index(CustomProp::Map(CustomPropsMap::default())), 10);
+        assert_eq!(variant_index(CustomProp::DoodadReference(DoodadId(0))), 11);
+    }
+
+    #[test]
+    fn binary_zone_file_rejects_other_versions() {
+        let mut bytes = encode_binary_zone_file(&test_zone_file()).unwrap();
+        bytes[4..8].copy_from_slice(&(CURRENT_ZONE_FORMAT_VERSION + 1).to_le_bytes());
+
+        assert!(decode_binary_zone_file(&bytes).is_err());
+        assert!(decode_binary_zone_file(b"nope").is_err());
+    }
+}
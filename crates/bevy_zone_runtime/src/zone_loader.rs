@@ -0,0 +1,202 @@
+use std::path::Path;
+
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+
+use crate::doodad_manifest::{DoodadComponent, DoodadManifest, DoodadManifestResource};
+use crate::zone_file::{CustomPropsComponent, ZoneFile, ZoneMetadataComponent};
+use crate::zone_file_format::{
+    decode_zone_file, zone_name_from_path, ZoneFileFormat, BINARY_ZONE_EXTENSION, RON_ZONE_EXTENSION,
+};
+
+/*
+
+Spawns zone files in a game.
+
+Put a ZoneHandle on an entity with a SpatialBundle.  Once the zone file and the doodad manifest
+in DoodadManifestResource are loaded, the entity gets the zone root transform and metadata,
+and every doodad is spawned under it with a Name, Transform, DoodadId, DoodadComponent and
+CustomPropsComponent, the same components the editor uses.
+
+Nothing is rendered here, the game decides what each RenderableType looks like, usually by
+spawning a scene for DoodadComponent GltfModel paths.
+
+Zone instances are loaded from the same folder as the zone that references them.  A zone is
+respawned when its file is reloaded, for example with the asset server file watcher on.
+
+*/
+
+//instances nested deeper than this are assumed to be a reference cycle
+const MAX_ZONE_INSTANCE_DEPTH: u32 = 16;
+
+#[derive(Default)]
+pub struct ZoneFileLoader;
+
+impl AssetLoader for ZoneFileLoader {
+    type Asset = ZoneFile;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let format = ZoneFileFormat::from_path(load_context.path());
+        let zone_name = zone_name_from_path(load_context.path()).unwrap_or_default();
+        let (zone_file, _) = decode_zone_file(&bytes, format, &zone_name)?;
+
+        Ok(zone_file)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[RON_ZONE_EXTENSION, BINARY_ZONE_EXTENSION]
+    }
+}
+
+/// Spawns the zone file as children of this entity.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ZoneHandle(pub Handle<ZoneFile>);
+
+/// Added once the zone of a ZoneHandle has been spawned.
+#[derive(Component)]
+pub struct SpawnedZone;
+
+/// How deeply a zone instance is nested in other zones.
+#[derive(Component)]
+pub struct ZoneInstanceDepth(pub u32);
+
+//referenced zones are next to the zone referencing them, in the same format
+fn zone_instance_path(host_path: &Path, zone_name: &str) -> std::path::PathBuf {
+    let format = ZoneFileFormat::from_path(host_path);
+
+    host_path.with_file_name(format!("{}.{}", zone_name, format.extension()))
+}
+
+pub fn spawn_zones(
+    mut commands: Commands,
+    zone_query: Query<(Entity, &ZoneHandle, Option<&ZoneInstanceDepth>), Without<SpawnedZone>>,
+    zone_files: Res<Assets<ZoneFile>>,
+    doodad_manifest_resource: Res<DoodadManifestResource>,
+    doodad_manifests: Res<Assets<DoodadManifest>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(manifest) = doodad_manifest_resource
+        .manifest
+        .as_ref()
+        .and_then(|handle| doodad_manifests.get(handle))
+    else {
+        return;
+    };
+
+    for (zone_root, zone_handle, instance_depth) in zone_query.iter() {
+        let Some(zone_file) = zone_files.get(&zone_handle.0) else {
+            continue;
+        };
+
+        let instance_depth = instance_depth.map_or(0, |depth| depth.0);
+
+        commands
+            .entity(zone_root)
+            .insert(SpawnedZone)
+            .insert(ZoneMetadataComponent {
+                metadata: zone_file.metadata.clone(),
+            });
+
+        //an instance is placed by the transform it was given in the zone referencing it
+        if instance_depth == 0 {
+            commands.entity(zone_root).insert(zone_file.get_root_transform());
+        }
+
+        let mut spawned_entities: Vec<Entity> = Vec::with_capacity(zone_file.entities.len());
+
+        for zone_entity in zone_file.entities.iter() {
+            //parents always come before their children in the file
+            let parent = zone_entity
+                .get_parent_index()
+                .and_then(|index| spawned_entities.get(index).copied())
+                .unwrap_or(zone_root);
+
+            let mut custom_props = CustomPropsComponent {
+                props: zone_entity.get_custom_props().clone().unwrap_or_default(),
+            };
+
+            let mut doodad = commands.spawn(SpatialBundle {
+                transform: zone_entity.transform.clone().into(),
+                ..default()
+            });
+            doodad.insert(Name::new(zone_entity.name.clone()));
+
+            if let Some(id) = zone_entity.get_id() {
+                doodad.insert(id);
+            }
+
+            match manifest.get_doodad_definition_by_name(&zone_entity.name) {
+                Some(definition) => {
+                    if let Some(initial_custom_props) = &definition.initial_custom_props {
+                        custom_props.set_custom_props_if_empty(initial_custom_props);
+                    }
+                    doodad.insert(DoodadComponent::from_definition(&definition));
+                }
+                None => warn!("zone doodad {:?} is not in the doodad manifest", zone_entity.name),
+            }
+
+            doodad.insert(custom_props).set_parent(parent);
+
+            spawned_entities.push(doodad.id());
+        }
+
+        if zone_file.zone_instances.is_empty() {
+            continue;
+        }
+
+        let Some(host_path) = zone_handle.0.path() else {
+            continue;
+        };
+
+        if instance_depth >= MAX_ZONE_INSTANCE_DEPTH {
+            warn!("zone instances in {:?} are nested too deeply, is there a cycle?", host_path);
+            continue;
+        }
+
+        for zone_instance in zone_file.zone_instances.iter() {
+            let instance_path = zone_instance_path(host_path.path(), &zone_instance.zone_name);
+
+            commands
+                .spawn(SpatialBundle {
+                    transform: zone_instance.transform.clone().into(),
+                    ..default()
+                })
+                .insert(Name::new(format!("{} (instance)", zone_instance.zone_name)))
+                .insert(ZoneHandle(asset_server.load(instance_path)))
+                .insert(ZoneInstanceDepth(instance_depth + 1))
+                .set_parent(zone_root);
+        }
+    }
+}
+
+/// Despawns the contents of zones whose file was reloaded, so spawn_zones spawns them again.
+pub fn respawn_changed_zones(
+    mut commands: Commands,
+    mut zone_file_events: EventReader<AssetEvent<ZoneFile>>,
+    zone_query: Query<(Entity, &ZoneHandle), With<SpawnedZone>>,
+) {
+    for event in zone_file_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+
+        for (zone_root, zone_handle) in zone_query.iter() {
+            if zone_handle.0.id() == *id {
+                commands
+                    .entity(zone_root)
+                    .despawn_descendants()
+                    .remove::<SpawnedZone>();
+            }
+        }
+    }
+}
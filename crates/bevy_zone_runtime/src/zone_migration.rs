@@ -0,0 +1,109 @@
+use serde::Deserialize;
+
+use crate::zone_file::{TransformSimple, ZoneFile};
+use crate::doodad_id::DoodadId;
+
+/*
+
+Zone files carry a format_version.  Files written before versioning existed have no
+version field and are treated as version 0.
+
+When the zone file layout changes, bump CURRENT_ZONE_FORMAT_VERSION and append a step to
+ZONE_MIGRATIONS that upgrades a file from the previous version.
+
+*/
+
+pub const CURRENT_ZONE_FORMAT_VERSION: u32 = 7;
+
+//migrations also get the zone name, the file stem without its format extension
+type ZoneMigration = fn(&mut ZoneFile, &str);
+
+//index N upgrades a file from version N to version N + 1
+const ZONE_MIGRATIONS: &[ZoneMigration] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+];
+
+//v1 only introduced the format_version field itself
+fn migrate_v0_to_v1(_zone_file: &mut ZoneFile, _zone_name: &str) {}
+
+//v2 stores rotations as quaternions, older files only have the euler angles
+fn migrate_v1_to_v2(zone_file: &mut ZoneFile, _zone_name: &str) {
+    for zone_entity in zone_file.entities.iter_mut() {
+        zone_entity.transform.rotation_quat =
+            TransformSimple::rotation_from_euler(zone_entity.transform.rotation);
+    }
+}
+
+//v3 records parent indices, older files are flat so every entity stays under the zone
+fn migrate_v2_to_v3(_zone_file: &mut ZoneFile, _zone_name: &str) {}
+
+//v4 adds the metadata block, older files get empty metadata
+fn migrate_v3_to_v4(_zone_file: &mut ZoneFile, _zone_name: &str) {}
+
+//v5 saves the zone root transform, older zones were always spawned at the origin
+fn migrate_v4_to_v5(_zone_file: &mut ZoneFile, _zone_name: &str) {}
+
+//v6 adds zone instances, older files do not reference other zones
+fn migrate_v5_to_v6(_zone_file: &mut ZoneFile, _zone_name: &str) {}
+
+//v7 gives every entity a persistent id.  The ids are derived from the zone name and entity
+//index, so every load of a file that has not been re-saved yet hands out the same ids
+fn migrate_v6_to_v7(zone_file: &mut ZoneFile, zone_name: &str) {
+    for (index, zone_entity) in zone_file.entities.iter_mut().enumerate() {
+        if zone_entity.id.is_none() {
+            zone_entity.id = Some(migrated_doodad_id(zone_name, index));
+        }
+    }
+}
+
+//FNV-1a, which unlike the std hasher is guaranteed to stay the same across builds
+fn migrated_doodad_id(zone_name: &str, index: usize) -> DoodadId {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in zone_name.bytes().chain((index as u64).to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    DoodadId(hash)
+}
+
+#[derive(Deserialize)]
+struct ZoneFileHeader {
+    #[serde(default)]
+    format_version: u32,
+}
+
+/// Parses a zone file and runs every migration needed to bring it to the current format.
+/// `zone_name` is the file stem, without its format extension.
+/// Returns the version the file was upgraded from, if it was upgraded.
+pub fn load_and_migrate_zone_file(
+    file_content: &str,
+    zone_name: &str,
+) -> Result<(ZoneFile, Option<u32>), Box<dyn std::error::Error + Send + Sync>> {
+    let header = ron::from_str::<ZoneFileHeader>(file_content)?;
+    let file_version = header.format_version;
+
+    if file_version > CURRENT_ZONE_FORMAT_VERSION {
+        return Err(format!(
+            "zone format version {} is newer than this version supports ({})",
+            file_version, CURRENT_ZONE_FORMAT_VERSION
+        )
+        .into());
+    }
+
+    let mut zone_file = ron::from_str::<ZoneFile>(file_content)?;
+
+    for version in file_version..CURRENT_ZONE_FORMAT_VERSION {
+        ZONE_MIGRATIONS[version as usize](&mut zone_file, zone_name);
+        zone_file.format_version = version + 1;
+    }
+
+    let upgraded_from = (file_version < CURRENT_ZONE_FORMAT_VERSION).then_some(file_version);
+
+    Ok((zone_file, upgraded_from))
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy_editor_pls_default_windows::zones::zone_file_format::{
+    convert_zone_file, zone_file_path_as, ZoneFileFormat,
+};
+
+/*
+
+Converts zone files between RON and the binary zone format.  The source files are kept, so
+authoring can stay in RON while a shipping build reads the binary copies.
+
+cargo run --bin convert_zones -- --to binary assets/zones
+cargo run --bin convert_zones -- --to binary --out build/zones assets/zones
+cargo run --bin convert_zones -- --to ron assets/zones/forest.zone.bin
+
+*/
+
+const USAGE: &str = "usage: convert_zones --to <binary|ron> [--out <dir>] <zone files or folders>";
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+fn collect_zone_files(
+    path: &Path,
+    format: ZoneFileFormat,
+    zone_files: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry_path = entry?.path();
+            if entry_path.is_file() {
+                collect_zone_files(&entry_path, format, zone_files)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let is_source_zone_file = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| name.ends_with(format.other().extension()));
+
+    if is_source_zone_file {
+        zone_files.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let mut target_format: Option<ZoneFileFormat> = None;
+    let mut output_dir: Option<PathBuf> = None;
+    let mut inputs: Vec<PathBuf> = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => {
+                target_format = match args.next().as_deref() {
+                    Some("binary") | Some("bin") => Some(ZoneFileFormat::Binary),
+                    Some("ron") => Some(ZoneFileFormat::Ron),
+                    Some(other) => exit_with_usage(&format!("unknown zone format {:?}", other)),
+                    None => exit_with_usage("--to needs a zone format"),
+                }
+            }
+            "--out" => output_dir = args.next().map(PathBuf::from),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    let Some(target_format) = target_format else {
+        exit_with_usage("missing --to");
+    };
+
+    let mut zone_files: Vec<PathBuf> = Vec::new();
+    for input in inputs.iter() {
+        if let Err(e) = collect_zone_files(input, target_format, &mut zone_files) {
+            eprintln!("could not read {:?}: {}", input, e);
+            std::process::exit(1);
+        }
+    }
+    zone_files.sort();
+
+    if let Some(output_dir) = &output_dir {
+        if let Err(e) = fs::create_dir_all(output_dir) {
+            eprintln!("could not create output directory {:?}: {}", output_dir, e);
+            std::process::exit(1);
+        }
+    }
+
+    let mut failed = 0;
+
+    for zone_file in zone_files.iter() {
+        let mut destination = match zone_file_path_as(zone_file, target_format) {
+            Ok(destination) => destination,
+            Err(e) => {
+                eprintln!("could not convert {:?}: {}", zone_file, e);
+                failed += 1;
+                continue;
+            }
+        };
+        if let (Some(output_dir), Some(file_name)) = (&output_dir, destination.file_name()) {
+            destination = output_dir.join(file_name);
+        }
+
+        match convert_zone_file(zone_file, &destination) {
+            Ok(()) => println!("{:?} -> {:?}", zone_file, destination),
+            Err(e) => {
+                eprintln!("could not convert {:?}: {}", zone_file, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("converted {} zone files", zone_files.len() - failed);
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
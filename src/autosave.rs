@@ -0,0 +1,576 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy_editor_pls_default_windows::zones::{
+    build_zone_file, zone_file_stem, ZoneComponent, ZoneDirty, ZoneEvent, ZoneFileQueries,
+};
+use bevy_mesh_terrain::chunk::{Chunk, ChunkData, ChunkHeightMapResource};
+use bevy_mesh_terrain::edit::TerrainCommandEvent;
+use bevy_regions::edit::RegionCommandEvent;
+use bevy_regions::regions::{RegionDataEvent, RegionsDataMapResource};
+
+use crate::asset_loading::AssetLoadState;
+use crate::brush_history::{update_height_map, update_splat_image};
+
+/*
+
+Periodically writes copies of unsaved zones and brushed terrain / region data to the recovery directory.
+
+If the editor goes down before those edits are saved, the next launch finds the recovery
+manifest and offers to restore the edits (as unsaved changes) or discard them.
+
+*/
+
+const RECOVERY_DIR: &str = "recovery";
+const RECOVERY_MANIFEST_FILE: &str = "recovery.ron";
+
+const DEFAULT_AUTOSAVE_INTERVAL_SECS: f32 = 120.0;
+
+//recovered terrain data still waiting for its chunk after this long without any progress is given up on,
+//the chunk is not part of the loaded terrain
+const RECOVERY_CHUNK_WAIT_SECS: f32 = 30.0;
+
+pub fn autosave_plugin(app: &mut App) {
+    app.init_resource::<AutosaveSettings>()
+        .init_resource::<UnsavedTerrainEdits>()
+        .init_resource::<RecoveryState>()
+        .add_systems(Startup, detect_recovery_files)
+        .add_systems(
+            Update,
+            (
+                clear_saved_terrain_edits,
+                autosave,
+                recovery_prompt,
+                apply_recovery.run_if(in_state(AssetLoadState::Complete)),
+            )
+                .chain(),
+        );
+}
+
+#[derive(Resource)]
+pub struct AutosaveSettings {
+    //zero disables autosave
+    pub interval_secs: f32,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: DEFAULT_AUTOSAVE_INTERVAL_SECS,
+        }
+    }
+}
+
+/// Terrain chunks and region data changed by brush strokes since the last save.
+#[derive(Resource, Default)]
+pub struct UnsavedTerrainEdits {
+    pub height_chunks: HashSet<u32>,
+    pub splat_chunks: HashSet<u32>,
+    pub regions: bool,
+}
+
+impl UnsavedTerrainEdits {
+    pub fn is_empty(&self) -> bool {
+        self.height_chunks.is_empty() && self.splat_chunks.is_empty() && !self.regions
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RecoveryZone {
+    pub zone_name: String,
+    pub file_name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RecoveryManifest {
+    pub saved_at_unix_secs: u64,
+    pub zones: Vec<RecoveryZone>,
+    pub height_chunks: Vec<u32>,
+    pub splat_chunks: Vec<u32>,
+    pub regions: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct RecoveryState {
+    //found on launch, waiting for the user to restore or discard it
+    pub found_manifest: Option<RecoveryManifest>,
+    //being restored, entries are removed as they are applied
+    pub pending_restore: Option<RecoveryManifest>,
+    //recovery data that could not be applied, its files are kept until the user has seen this
+    pub restore_errors: Vec<String>,
+}
+
+impl RecoveryState {
+    //recovery files that are still needed, they must not be overwritten or removed
+    pub fn is_busy(&self) -> bool {
+        self.found_manifest.is_some() || self.pending_restore.is_some() || !self.restore_errors.is_empty()
+    }
+}
+
+pub fn remove_recovery_files() {
+    if Path::new(RECOVERY_DIR).exists() {
+        if let Err(e) = fs::remove_dir_all(RECOVERY_DIR) {
+            eprintln!("could not remove recovery files {:?}", e);
+        }
+    }
+}
+
+// --------------------------------------------------------
+
+//terrain and region data is stored raw as (width: u32, height: u32, bytes) so it restores exactly
+fn write_recovery_blob(path: &Path, width: usize, height: usize, bytes: &[u8]) -> std::io::Result<()> {
+    let mut data = Vec::with_capacity(8 + bytes.len());
+    data.extend_from_slice(&(width as u32).to_le_bytes());
+    data.extend_from_slice(&(height as u32).to_le_bytes());
+    data.extend_from_slice(bytes);
+    fs::write(path, data)
+}
+
+fn read_recovery_blob(path: &Path) -> Option<(usize, usize, Vec<u8>)> {
+    let data = fs::read(path).ok()?;
+    if data.len() < 8 {
+        return None;
+    }
+
+    let width = u32::from_le_bytes(data[0..4].try_into().ok()?) as usize;
+    let height = u32::from_le_bytes(data[4..8].try_into().ok()?) as usize;
+
+    Some((width, height, data[8..].to_vec()))
+}
+
+fn height_map_to_bytes(height_map: &[Vec<u16>]) -> Vec<u8> {
+    height_map
+        .iter()
+        .flatten()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn bytes_to_grid<T>(width: usize, bytes: &[u8], size_of: usize, read: impl Fn(&[u8]) -> T) -> Vec<Vec<T>> {
+    if width == 0 {
+        return Vec::new();
+    }
+
+    bytes
+        .chunks_exact(width * size_of)
+        .map(|row| row.chunks_exact(size_of).map(&read).collect())
+        .collect()
+}
+
+fn height_chunk_path(dir: &Path, chunk_id: u32) -> PathBuf {
+    dir.join("terrain").join("height").join(format!("{}.bin", chunk_id))
+}
+
+fn splat_chunk_path(dir: &Path, chunk_id: u32) -> PathBuf {
+    dir.join("terrain").join("splat").join(format!("{}.bin", chunk_id))
+}
+
+fn regions_path(dir: &Path) -> PathBuf {
+    dir.join("regions.bin")
+}
+
+// --------------------------------------------------------
+
+fn clear_saved_terrain_edits(
+    mut terrain_command_reader: EventReader<TerrainCommandEvent>,
+    mut region_command_reader: EventReader<RegionCommandEvent>,
+
+    mut unsaved_terrain_edits: ResMut<UnsavedTerrainEdits>,
+) {
+    for evt in terrain_command_reader.read() {
+        match evt {
+            TerrainCommandEvent::SaveAllChunks(save_height, save_splat, _) => {
+                if *save_height {
+                    unsaved_terrain_edits.height_chunks.clear();
+                }
+                if *save_splat {
+                    unsaved_terrain_edits.splat_chunks.clear();
+                }
+            }
+        }
+    }
+
+    for evt in region_command_reader.read() {
+        match evt {
+            RegionCommandEvent::SaveAll => unsaved_terrain_edits.regions = false,
+        }
+    }
+}
+
+fn autosave(
+    time: Res<Time>,
+    mut since_last_autosave: Local<f32>,
+
+    autosave_settings: Res<AutosaveSettings>,
+    recovery_state: Res<RecoveryState>,
+
+    dirty_zone_query: Query<(Entity, &Name), (With<ZoneComponent>, With<ZoneDirty>)>,
+    zone_file_queries: ZoneFileQueries,
+
+    unsaved_terrain_edits: Res<UnsavedTerrainEdits>,
+    height_maps: Res<ChunkHeightMapResource>,
+    chunk_query: Query<(&Chunk, &ChunkData)>,
+    images: Res<Assets<Image>>,
+    regions_data: Res<RegionsDataMapResource>,
+) {
+    //never overwrite recovery files the user has not decided about yet
+    if autosave_settings.interval_secs <= 0.0 || recovery_state.is_busy() {
+        return;
+    }
+
+    *since_last_autosave += time.delta_seconds();
+    if *since_last_autosave < autosave_settings.interval_secs {
+        return;
+    }
+    *since_last_autosave = 0.0;
+
+    if dirty_zone_query.is_empty() && unsaved_terrain_edits.is_empty() {
+        remove_recovery_files();
+        return;
+    }
+
+    //write everything to a temp dir first so a crash mid-write keeps the previous snapshot
+    let temp_dir = PathBuf::from(format!("{}_tmp", RECOVERY_DIR));
+    let _ = fs::remove_dir_all(&temp_dir);
+
+    let result = (|| -> Result<RecoveryManifest, Box<dyn std::error::Error + Send + Sync>> {
+        let mut manifest = RecoveryManifest {
+            saved_at_unix_secs: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            ..default()
+        };
+
+        fs::create_dir_all(temp_dir.join("zones"))?;
+        fs::create_dir_all(temp_dir.join("terrain").join("height"))?;
+        fs::create_dir_all(temp_dir.join("terrain").join("splat"))?;
+
+        for (zone_entity, zone_name) in dirty_zone_query.iter() {
+            let zone_file = build_zone_file(zone_entity, &zone_file_queries);
+
+            let file_name = format!("{}.zone.ron", zone_file_stem(zone_name.as_str()));
+            fs::write(temp_dir.join("zones").join(&file_name), zone_file.to_ron_string()?)?;
+
+            manifest.zones.push(RecoveryZone {
+                zone_name: zone_name.as_str().to_string(),
+                file_name,
+            });
+        }
+
+        for chunk_id in unsaved_terrain_edits.height_chunks.iter() {
+            let Some(height_map) = height_maps.chunk_height_maps.get(chunk_id) else {
+                continue;
+            };
+            let width = height_map.first().map_or(0, |row| row.len());
+
+            write_recovery_blob(
+                &height_chunk_path(&temp_dir, *chunk_id),
+                width,
+                height_map.len(),
+                &height_map_to_bytes(height_map),
+            )?;
+            manifest.height_chunks.push(*chunk_id);
+        }
+
+        for (chunk, chunk_data) in chunk_query.iter() {
+            if !unsaved_terrain_edits.splat_chunks.contains(&chunk.chunk_id) {
+                continue;
+            }
+            let Some(image) = chunk_data
+                .get_splat_texture_image()
+                .as_ref()
+                .and_then(|handle| images.get(handle))
+            else {
+                continue;
+            };
+            let size = image.size();
+
+            write_recovery_blob(
+                &splat_chunk_path(&temp_dir, chunk.chunk_id),
+                size.x as usize,
+                size.y as usize,
+                &image.data,
+            )?;
+            manifest.splat_chunks.push(chunk.chunk_id);
+        }
+
+        if unsaved_terrain_edits.regions {
+            if let Some(region_map) = &regions_data.regions_data_map {
+                let width = region_map.first().map_or(0, |row| row.len());
+                let bytes: Vec<u8> = region_map.iter().flatten().copied().collect();
+
+                write_recovery_blob(&regions_path(&temp_dir), width, region_map.len(), &bytes)?;
+                manifest.regions = true;
+            }
+        }
+
+        let manifest_ron = ron::ser::to_string_pretty(&manifest, ron::ser::PrettyConfig::default())?;
+        fs::write(temp_dir.join(RECOVERY_MANIFEST_FILE), manifest_ron)?;
+
+        remove_recovery_files();
+        fs::rename(&temp_dir, RECOVERY_DIR)?;
+
+        Ok(manifest)
+    })();
+
+    match result {
+        Ok(manifest) => info!(
+            "autosaved {} zones, {} height chunks, {} splat chunks to {}",
+            manifest.zones.len(),
+            manifest.height_chunks.len(),
+            manifest.splat_chunks.len(),
+            RECOVERY_DIR
+        ),
+        Err(e) => eprintln!("autosave failed {:?}", e),
+    }
+}
+
+// --------------------------------------------------------
+
+fn detect_recovery_files(mut recovery_state: ResMut<RecoveryState>) {
+    let manifest_path = Path::new(RECOVERY_DIR).join(RECOVERY_MANIFEST_FILE);
+
+    let Ok(manifest_ron) = fs::read_to_string(&manifest_path) else {
+        return;
+    };
+
+    match ron::from_str::<RecoveryManifest>(&manifest_ron) {
+        Ok(manifest) => {
+            info!("found recovery files from a previous session");
+            recovery_state.found_manifest = Some(manifest);
+        }
+        Err(e) => eprintln!("could not parse recovery manifest {:?}", e),
+    }
+}
+
+fn recovery_prompt(mut contexts: EguiContexts, mut recovery_state: ResMut<RecoveryState>) {
+    if recovery_state.pending_restore.is_none() && !recovery_state.restore_errors.is_empty() {
+        egui::Window::new("Recovery Incomplete")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(contexts.ctx_mut(), |ui| {
+                ui.label("Some autosaved edits could not be restored:");
+
+                for message in recovery_state.restore_errors.iter() {
+                    ui.colored_label(egui::Color32::RED, format!("* {}", message));
+                }
+
+                ui.label(format!(
+                    "The recovery files are kept in {}/ until this is dismissed.",
+                    RECOVERY_DIR
+                ));
+
+                if ui.button("Dismiss").clicked() {
+                    recovery_state.restore_errors.clear();
+                }
+            });
+    }
+
+    let Some(manifest) = recovery_state.found_manifest.clone() else {
+        return;
+    };
+
+    egui::Window::new("Recover Unsaved Work")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("The editor did not shut down cleanly. Autosaved edits were found:");
+
+            for zone in manifest.zones.iter() {
+                ui.label(format!("* zone {}", zone.zone_name));
+            }
+            if !manifest.height_chunks.is_empty() {
+                ui.label(format!("* height data for {} chunks", manifest.height_chunks.len()));
+            }
+            if !manifest.splat_chunks.is_empty() {
+                ui.label(format!("* splat data for {} chunks", manifest.splat_chunks.len()));
+            }
+            if manifest.regions {
+                ui.label("* region data");
+            }
+
+            let age_secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+                .saturating_sub(manifest.saved_at_unix_secs);
+            ui.label(format!("Autosaved {} minutes ago", age_secs / 60));
+
+            ui.horizontal(|ui| {
+                if ui.button("Restore").clicked() {
+                    recovery_state.pending_restore = recovery_state.found_manifest.take();
+                }
+
+                if ui.button("Discard").clicked() {
+                    recovery_state.found_manifest = None;
+                    remove_recovery_files();
+                }
+            });
+        });
+}
+
+//terrain chunks stream in after startup, so entries stay pending until their chunk is loaded
+fn apply_recovery(world: &mut World, mut secs_without_progress: Local<f32>) {
+    let Some(mut manifest) = world.resource_mut::<RecoveryState>().pending_restore.take() else {
+        *secs_without_progress = 0.0;
+        return;
+    };
+    let pending_before = manifest.height_chunks.len() + manifest.splat_chunks.len() + manifest.regions as usize;
+
+    let recovery_dir = PathBuf::from(RECOVERY_DIR);
+
+    for zone in manifest.zones.drain(..) {
+        let recovery_file = recovery_dir.join("zones").join(&zone.file_name);
+
+        world.send_event(ZoneEvent::RestoreZoneFromRecovery {
+            zone_name: zone.zone_name,
+            recovery_file: recovery_file.to_string_lossy().to_string(),
+        });
+    }
+
+    manifest.height_chunks.retain(|chunk_id| {
+        let Some((width, height, bytes)) = read_recovery_blob(&height_chunk_path(&recovery_dir, *chunk_id)) else {
+            eprintln!("missing height recovery data for chunk {}", chunk_id);
+            return false;
+        };
+        let restored = bytes_to_grid(width, &bytes, 2, |b| u16::from_le_bytes([b[0], b[1]]));
+
+        let mut loaded_size = None;
+        let applied = update_height_map(world, *chunk_id, |height_map| {
+            let loaded_width = height_map.first().map_or(0, |row| row.len());
+            if loaded_width == width && height_map.len() == height && restored.len() == height {
+                *height_map = restored;
+            } else {
+                loaded_size = Some((loaded_width, height_map.len()));
+            }
+        });
+
+        if let Some((loaded_width, loaded_height)) = loaded_size {
+            let message = format!(
+                "height data for chunk {} is {}x{} but the loaded height map is {}x{}",
+                chunk_id, width, height, loaded_width, loaded_height
+            );
+            eprintln!("could not restore {}", message);
+            world.resource_mut::<RecoveryState>().restore_errors.push(message);
+            return false;
+        }
+
+        if applied {
+            world
+                .resource_mut::<UnsavedTerrainEdits>()
+                .height_chunks
+                .insert(*chunk_id);
+        }
+        !applied
+    });
+
+    manifest.splat_chunks.retain(|chunk_id| {
+        let Some((width, height, bytes)) = read_recovery_blob(&splat_chunk_path(&recovery_dir, *chunk_id)) else {
+            eprintln!("missing splat recovery data for chunk {}", chunk_id);
+            return false;
+        };
+
+        let mut loaded_size = None;
+        let applied = update_splat_image(world, *chunk_id, |image| {
+            let size = image.size();
+            if size.x as usize == width && size.y as usize == height && image.data.len() == bytes.len() {
+                image.data = bytes;
+            } else {
+                loaded_size = Some(size);
+            }
+        });
+
+        if let Some(size) = loaded_size {
+            let message = format!(
+                "splat data for chunk {} is {}x{} but the loaded splat map is {}x{}",
+                chunk_id, width, height, size.x, size.y
+            );
+            eprintln!("could not restore {}", message);
+            world.resource_mut::<RecoveryState>().restore_errors.push(message);
+            return false;
+        }
+
+        if applied {
+            world
+                .resource_mut::<UnsavedTerrainEdits>()
+                .splat_chunks
+                .insert(*chunk_id);
+        }
+        !applied
+    });
+
+    if manifest.regions {
+        match read_recovery_blob(&regions_path(&recovery_dir)) {
+            Some((width, _, bytes)) => {
+                let mut regions_data = world.resource_mut::<RegionsDataMapResource>();
+
+                if regions_data.regions_data_map.is_some() {
+                    regions_data.regions_data_map = Some(bytes_to_grid(width, &bytes, 1, |b| b[0]));
+                    world.send_event(RegionDataEvent::RegionMapNeedsReloadFromResourceData);
+                    world.resource_mut::<UnsavedTerrainEdits>().regions = true;
+                    manifest.regions = false;
+                }
+            }
+            None => {
+                eprintln!("missing region recovery data");
+                manifest.regions = false;
+            }
+        }
+    }
+
+    let pending_after = manifest.height_chunks.len() + manifest.splat_chunks.len() + manifest.regions as usize;
+    match pending_after < pending_before {
+        true => *secs_without_progress = 0.0,
+        false => *secs_without_progress += world.resource::<Time>().delta_seconds(),
+    }
+
+    //the rest belongs to chunks or region data that never loaded, like after switching terrains
+    if *secs_without_progress >= RECOVERY_CHUNK_WAIT_SECS {
+        let mut messages: Vec<String> = Vec::new();
+        messages.extend(
+            manifest
+                .height_chunks
+                .drain(..)
+                .map(|chunk_id| format!("height data for chunk {}, the chunk never loaded", chunk_id)),
+        );
+        messages.extend(
+            manifest
+                .splat_chunks
+                .drain(..)
+                .map(|chunk_id| format!("splat data for chunk {}, the chunk never loaded", chunk_id)),
+        );
+        if manifest.regions {
+            messages.push("region data, no region map was loaded".to_string());
+            manifest.regions = false;
+        }
+
+        for message in messages.iter() {
+            eprintln!("could not restore {}", message);
+        }
+        world.resource_mut::<RecoveryState>().restore_errors.extend(messages);
+        *secs_without_progress = 0.0;
+    }
+
+    let done = manifest.height_chunks.is_empty() && manifest.splat_chunks.is_empty() && !manifest.regions;
+
+    if done {
+        //the restored data is tracked as unsaved again, the next autosave rewrites the recovery files.
+        //they are not removed here since the zone files are read when the restore events are handled
+        match world.resource::<RecoveryState>().restore_errors.is_empty() {
+            true => info!("restored autosaved edits"),
+            false => warn!("restored autosaved edits, some could not be applied"),
+        }
+    } else {
+        world.resource_mut::<RecoveryState>().pending_restore = Some(manifest);
+    }
+}
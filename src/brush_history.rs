@@ -0,0 +1,498 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use bevy_editor_pls_default_windows::history::{EditorCommand, EditorHistory, EntityRemaps};
+use bevy_mesh_terrain::chunk::{Chunk, ChunkData, ChunkHeightMapResource, TerrainMaterialExtension};
+use bevy_mesh_terrain::edit::{BrushType as TerrainBrushType, EditTerrainEvent, EditingTool as TerrainEditingTool};
+use bevy_mesh_terrain::terrain::TerrainImageDataLoadStatus;
+use bevy_mesh_terrain::terrain_config::TerrainConfig;
+use bevy_regions::edit::{BrushType as RegionsBrushType, EditRegionEvent};
+use bevy_regions::regions::{RegionDataEvent, RegionsDataMapResource};
+
+use crate::autosave::UnsavedTerrainEdits;
+use crate::tools::update_brush_paint;
+
+/*
+
+Records each brush stroke (mouse down to mouse up) as a single undoable command.
+
+Only the tiles of the height maps, splat maps and region map that the brush touched are stored,
+captured right before the first edit that touches them and again once the stroke is finished.
+
+*/
+
+const TILE_SIZE: usize = 32;
+
+pub fn brush_history_plugin(app: &mut App) {
+    app.init_resource::<BrushStrokeRecorder>().add_systems(
+        Update,
+        record_brush_strokes
+            .after(update_brush_paint)
+            .before(bevy_mesh_terrain::edit::apply_tool_edits)
+            .before(bevy_regions::edit::apply_tool_edits),
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum BrushLayer {
+    Height { chunk_id: u32 },
+    Splat { chunk_id: u32 },
+    Regions,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct TileKey {
+    layer: BrushLayer,
+    tile_x: usize,
+    tile_y: usize,
+}
+
+#[derive(Clone)]
+enum TileData {
+    Height(Vec<u16>),
+    //rgba bytes
+    Splat(Vec<u8>),
+    Regions(Vec<u8>),
+}
+
+impl TileData {
+    fn memory_size(&self) -> usize {
+        match self {
+            TileData::Height(data) => data.len() * std::mem::size_of::<u16>(),
+            TileData::Splat(data) => data.len(),
+            TileData::Regions(data) => data.len(),
+        }
+    }
+}
+
+/// Tracks the stroke that is currently being painted.
+#[derive(Resource, Default)]
+pub struct BrushStrokeRecorder {
+    label: Option<String>,
+    before: HashMap<TileKey, TileData>,
+}
+
+struct BrushTile {
+    key: TileKey,
+    before: TileData,
+    after: TileData,
+}
+
+pub struct BrushStrokeCommand {
+    label: String,
+    tiles: Vec<BrushTile>,
+}
+
+impl BrushStrokeCommand {
+    fn apply(&self, world: &mut World, use_before: bool) {
+        let mut regions_changed = false;
+
+        for tile in self.tiles.iter() {
+            let data = match use_before {
+                true => &tile.before,
+                false => &tile.after,
+            };
+
+            match (tile.key.layer, data) {
+                (BrushLayer::Height { chunk_id }, TileData::Height(data)) => {
+                    write_height_tile(world, chunk_id, &tile.key, data);
+                }
+                (BrushLayer::Splat { chunk_id }, TileData::Splat(data)) => {
+                    write_splat_tile(world, chunk_id, &tile.key, data);
+                }
+                (BrushLayer::Regions, TileData::Regions(data)) => {
+                    write_regions_tile(world, &tile.key, data);
+                    regions_changed = true;
+                }
+                _ => warn!("mismatched brush history tile {:?}", tile.key),
+            }
+        }
+
+        if regions_changed {
+            world.send_event(RegionDataEvent::RegionMapNeedsReloadFromResourceData);
+        }
+
+        let mut unsaved_terrain_edits = world.resource_mut::<UnsavedTerrainEdits>();
+        for tile in self.tiles.iter() {
+            mark_unsaved(&mut unsaved_terrain_edits, tile.key.layer);
+        }
+    }
+}
+
+fn mark_unsaved(unsaved_terrain_edits: &mut UnsavedTerrainEdits, layer: BrushLayer) {
+    match layer {
+        BrushLayer::Height { chunk_id } => {
+            unsaved_terrain_edits.height_chunks.insert(chunk_id);
+        }
+        BrushLayer::Splat { chunk_id } => {
+            unsaved_terrain_edits.splat_chunks.insert(chunk_id);
+        }
+        BrushLayer::Regions => unsaved_terrain_edits.regions = true,
+    }
+}
+
+impl EditorCommand for BrushStrokeCommand {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn undo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, true);
+    }
+
+    fn redo(&mut self, world: &mut World, _remaps: &mut EntityRemaps) {
+        self.apply(world, false);
+    }
+
+    fn memory_size(&self) -> usize {
+        self.tiles
+            .iter()
+            .map(|tile| tile.before.memory_size() + tile.after.memory_size())
+            .sum()
+    }
+}
+
+// --------------------------------------------------------
+
+/// Returns the tile coordinates covering the square around `center` (in data space) clamped to `dimensions`.
+fn tiles_in_range(center: Vec2, radius: f32, dimensions: UVec2) -> Vec<(usize, usize)> {
+    if dimensions.x == 0 || dimensions.y == 0 {
+        return Vec::new();
+    }
+
+    let min = (center - Vec2::splat(radius)).floor();
+    let max = (center + Vec2::splat(radius)).ceil();
+
+    if max.x < 0.0 || max.y < 0.0 || min.x >= dimensions.x as f32 || min.y >= dimensions.y as f32 {
+        return Vec::new();
+    }
+
+    let min_x = min.x.max(0.0) as usize / TILE_SIZE;
+    let min_y = min.y.max(0.0) as usize / TILE_SIZE;
+    let max_x = (max.x as usize).min(dimensions.x as usize - 1) / TILE_SIZE;
+    let max_y = (max.y as usize).min(dimensions.y as usize - 1) / TILE_SIZE;
+
+    let mut tiles = Vec::new();
+    for tile_y in min_y..=max_y {
+        for tile_x in min_x..=max_x {
+            tiles.push((tile_x, tile_y));
+        }
+    }
+    tiles
+}
+
+/// Row-major index ranges of a tile, clamped to the data dimensions.
+fn tile_bounds(key: &TileKey, width: usize, height: usize) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+    let x0 = (key.tile_x * TILE_SIZE).min(width);
+    let y0 = (key.tile_y * TILE_SIZE).min(height);
+    let x1 = (x0 + TILE_SIZE).min(width);
+    let y1 = (y0 + TILE_SIZE).min(height);
+    (x0..x1, y0..y1)
+}
+
+fn read_grid_tile<T: Copy>(grid: &[Vec<T>], key: &TileKey) -> Vec<T> {
+    let width = grid.first().map_or(0, |row| row.len());
+    let (xs, ys) = tile_bounds(key, width, grid.len());
+
+    let mut data = Vec::with_capacity(xs.len() * ys.len());
+    for y in ys {
+        data.extend_from_slice(&grid[y][xs.clone()]);
+    }
+    data
+}
+
+fn write_grid_tile<T: Copy>(grid: &mut [Vec<T>], key: &TileKey, data: &[T]) {
+    let width = grid.first().map_or(0, |row| row.len());
+    let (xs, ys) = tile_bounds(key, width, grid.len());
+
+    let row_len = xs.len();
+    for (row, y) in ys.enumerate() {
+        let Some(src) = data.get(row * row_len..(row + 1) * row_len) else {
+            return;
+        };
+        grid[y][xs.clone()].copy_from_slice(src);
+    }
+}
+
+fn read_image_tile(image: &Image, key: &TileKey) -> Vec<u8> {
+    let size = image.size();
+    let (xs, ys) = tile_bounds(key, size.x as usize, size.y as usize);
+
+    let mut data = Vec::with_capacity(xs.len() * ys.len() * 4);
+    for y in ys {
+        let row_start = (y * size.x as usize + xs.start) * 4;
+        let row_end = (y * size.x as usize + xs.end) * 4;
+        data.extend_from_slice(&image.data[row_start..row_end]);
+    }
+    data
+}
+
+fn write_image_tile(image: &mut Image, key: &TileKey, data: &[u8]) {
+    let size = image.size();
+    let (xs, ys) = tile_bounds(key, size.x as usize, size.y as usize);
+
+    let row_len = xs.len() * 4;
+    for (row, y) in ys.enumerate() {
+        let Some(src) = data.get(row * row_len..(row + 1) * row_len) else {
+            return;
+        };
+        let row_start = (y * size.x as usize + xs.start) * 4;
+        image.data[row_start..row_start + row_len].copy_from_slice(src);
+    }
+}
+
+fn write_height_tile(world: &mut World, chunk_id: u32, key: &TileKey, data: &[u16]) {
+    if !update_height_map(world, chunk_id, |height_map| write_grid_tile(height_map, key, data)) {
+        warn!("no height map loaded for chunk {}", chunk_id);
+    }
+}
+
+fn write_splat_tile(world: &mut World, chunk_id: u32, key: &TileKey, data: &[u8]) {
+    if !update_splat_image(world, chunk_id, |image| write_image_tile(image, key, data)) {
+        warn!("no splat map loaded for chunk {}", chunk_id);
+    }
+}
+
+/// Edits a chunk height map and flags the chunk mesh for a rebuild. Returns false if the chunk is not loaded.
+pub(crate) fn update_height_map(
+    world: &mut World,
+    chunk_id: u32,
+    edit: impl FnOnce(&mut Vec<Vec<u16>>),
+) -> bool {
+    let mut height_maps = world.resource_mut::<ChunkHeightMapResource>();
+    let Some(height_map) = height_maps.chunk_height_maps.get_mut(&chunk_id) else {
+        return false;
+    };
+    edit(height_map);
+
+    let mut chunk_query = world.query::<(&Chunk, &mut ChunkData)>();
+    for (chunk, mut chunk_data) in chunk_query.iter_mut(world) {
+        if chunk.chunk_id == chunk_id {
+            chunk_data.height_map_image_data_load_status = TerrainImageDataLoadStatus::NeedsReload;
+        }
+    }
+
+    true
+}
+
+/// Edits a chunk splat image and swaps it into the terrain material. Returns false if the chunk is not loaded.
+pub(crate) fn update_splat_image(
+    world: &mut World,
+    chunk_id: u32,
+    edit: impl FnOnce(&mut Image),
+) -> bool {
+    let mut chunk_query = world.query::<(Entity, &Chunk, &ChunkData)>();
+    let Some((chunk_entity, splat_handle, material_handle)) = chunk_query
+        .iter(world)
+        .find(|(_, chunk, _)| chunk.chunk_id == chunk_id)
+        .and_then(|(entity, _, chunk_data)| {
+            chunk_data
+                .get_splat_texture_image()
+                .clone()
+                .map(|splat| (entity, splat, chunk_data.material_handle.clone()))
+        })
+    else {
+        return false;
+    };
+
+    let mut images = world.resource_mut::<Assets<Image>>();
+    let Some(image) = images.get_mut(&splat_handle) else {
+        return false;
+    };
+    edit(image);
+
+    //same as the terrain brush: swap in a fresh image so the material picks up the change
+    let updated_image = image.clone();
+    let updated_image_handle = images.add(updated_image);
+
+    if let Some(mut chunk_data) = world.get_mut::<ChunkData>(chunk_entity) {
+        chunk_data.set_splat_texture_image(updated_image_handle.clone());
+    }
+
+    if let Some(material_handle) = material_handle {
+        let mut terrain_materials = world.resource_mut::<Assets<TerrainMaterialExtension>>();
+        if let Some(terrain_material) = terrain_materials.get_mut(&material_handle) {
+            terrain_material.extension.splat_texture = Some(updated_image_handle);
+        }
+    }
+
+    true
+}
+
+fn write_regions_tile(world: &mut World, key: &TileKey, data: &[u8]) {
+    let mut regions_data = world.resource_mut::<RegionsDataMapResource>();
+    if let Some(region_map) = &mut regions_data.regions_data_map {
+        write_grid_tile(region_map, key, data);
+    }
+}
+
+// --------------------------------------------------------
+
+fn record_brush_strokes(
+    mut recorder: ResMut<BrushStrokeRecorder>,
+    mut history: ResMut<EditorHistory>,
+    mut unsaved_terrain_edits: ResMut<UnsavedTerrainEdits>,
+
+    mut terrain_edit_reader: EventReader<EditTerrainEvent>,
+    mut region_edit_reader: EventReader<EditRegionEvent>,
+
+    mouse_input: Res<ButtonInput<MouseButton>>,
+
+    chunk_query: Query<(&Chunk, &ChunkData, &Parent, &GlobalTransform)>,
+    terrain_config_query: Query<&TerrainConfig>,
+    height_maps: Res<ChunkHeightMapResource>,
+    regions_data: Res<RegionsDataMapResource>,
+    images: Res<Assets<Image>>,
+) {
+    for evt in terrain_edit_reader.read() {
+        if evt.brush_type == TerrainBrushType::EyeDropper {
+            continue;
+        }
+
+        let is_height = matches!(evt.tool, TerrainEditingTool::SetHeightMap { .. });
+
+        recorder.label.get_or_insert_with(|| match is_height {
+            true => format!("Height stroke ({:?})", evt.brush_type),
+            false => "Splat stroke".to_string(),
+        });
+
+        for (chunk, chunk_data, terrain_entity, chunk_xform) in chunk_query.iter() {
+            let chunk_translation = chunk_xform.translation();
+            let tool_coords_local = evt.coordinates - Vec2::new(chunk_translation.x, chunk_translation.z);
+
+            if is_height {
+                let Some(height_map) = height_maps.chunk_height_maps.get(&chunk.chunk_id) else {
+                    continue;
+                };
+                let dimensions = UVec2::new(
+                    height_map.first().map_or(0, |row| row.len()) as u32,
+                    height_map.len() as u32,
+                );
+
+                for (tile_x, tile_y) in tiles_in_range(tool_coords_local, evt.radius, dimensions) {
+                    let key = TileKey {
+                        layer: BrushLayer::Height { chunk_id: chunk.chunk_id },
+                        tile_x,
+                        tile_y,
+                    };
+                    recorder
+                        .before
+                        .entry(key)
+                        .or_insert_with(|| TileData::Height(read_grid_tile(height_map, &key)));
+                }
+            } else {
+                let Some(image) = chunk_data
+                    .get_splat_texture_image()
+                    .as_ref()
+                    .and_then(|handle| images.get(handle))
+                else {
+                    continue;
+                };
+                let Ok(terrain_config) = terrain_config_query.get(terrain_entity.get()) else {
+                    continue;
+                };
+
+                //same mapping as the splat brush in bevy_mesh_terrain
+                let chunk_dimensions = terrain_config.terrain_dimensions / terrain_config.chunk_rows as f32;
+                let img_size = image.size();
+                let pixel_pos = tool_coords_local / chunk_dimensions * img_size.as_vec2();
+
+                for (tile_x, tile_y) in tiles_in_range(pixel_pos, evt.radius, img_size) {
+                    let key = TileKey {
+                        layer: BrushLayer::Splat { chunk_id: chunk.chunk_id },
+                        tile_x,
+                        tile_y,
+                    };
+                    recorder
+                        .before
+                        .entry(key)
+                        .or_insert_with(|| TileData::Splat(read_image_tile(image, &key)));
+                }
+            }
+        }
+    }
+
+    for evt in region_edit_reader.read() {
+        if evt.brush_type == RegionsBrushType::EyeDropper {
+            continue;
+        }
+
+        let Some(region_map) = &regions_data.regions_data_map else {
+            continue;
+        };
+
+        recorder.label.get_or_insert_with(|| "Region stroke".to_string());
+
+        let dimensions = UVec2::new(
+            region_map.first().map_or(0, |row| row.len()) as u32,
+            region_map.len() as u32,
+        );
+
+        for (tile_x, tile_y) in tiles_in_range(evt.coordinates, evt.radius, dimensions) {
+            let key = TileKey {
+                layer: BrushLayer::Regions,
+                tile_x,
+                tile_y,
+            };
+            recorder
+                .before
+                .entry(key)
+                .or_insert_with(|| TileData::Regions(read_grid_tile(region_map, &key)));
+        }
+    }
+
+    //every edit of the stroke has been applied by the time the button is released
+    if mouse_input.pressed(MouseButton::Left) || recorder.before.is_empty() {
+        return;
+    }
+
+    let label = recorder.label.take().unwrap_or_else(|| "Brush stroke".to_string());
+    let mut tiles = Vec::new();
+
+    for (key, before) in recorder.before.drain() {
+        let after = match key.layer {
+            BrushLayer::Height { chunk_id } => height_maps
+                .chunk_height_maps
+                .get(&chunk_id)
+                .map(|height_map| TileData::Height(read_grid_tile(height_map, &key))),
+            BrushLayer::Splat { chunk_id } => chunk_query
+                .iter()
+                .find(|(chunk, ..)| chunk.chunk_id == chunk_id)
+                .and_then(|(_, chunk_data, ..)| chunk_data.get_splat_texture_image().as_ref())
+                .and_then(|handle| images.get(handle))
+                .map(|image| TileData::Splat(read_image_tile(image, &key))),
+            BrushLayer::Regions => regions_data
+                .regions_data_map
+                .as_ref()
+                .map(|region_map| TileData::Regions(read_grid_tile(region_map, &key))),
+        };
+
+        let Some(after) = after else {
+            continue;
+        };
+
+        //tiles inside the brush bounds that the round brush never reached
+        let unchanged = match (&before, &after) {
+            (TileData::Height(a), TileData::Height(b)) => a == b,
+            (TileData::Splat(a), TileData::Splat(b)) => a == b,
+            (TileData::Regions(a), TileData::Regions(b)) => a == b,
+            _ => false,
+        };
+        if unchanged {
+            continue;
+        }
+
+        tiles.push(BrushTile { key, before, after });
+    }
+
+    if tiles.is_empty() {
+        return;
+    }
+
+    for tile in tiles.iter() {
+        mark_unsaved(&mut unsaved_terrain_edits, tile.key.layer);
+    }
+
+    info!("recorded {} with {} tiles", label, tiles.len());
+    history.push(BrushStrokeCommand { label, tiles });
+}
@@ -12,6 +12,7 @@ use bevy_mesh_terrain::{
 
 use bevy_mod_raycast::prelude::*;
 use bevy_regions::edit::RegionCommandEvent;
+use bevy_editor_pls_default_windows::zones::ZoneEvent;
 
 pub fn update_commands(
     key_input: Res<ButtonInput<KeyCode>>, //detect mouse click
@@ -19,6 +20,7 @@ pub fn update_commands(
    // mut edit_event_writer: EventWriter<EditTerrainEvent>,
     mut command_event_writer: EventWriter<TerrainCommandEvent>,
     mut region_command_event_writer: EventWriter<RegionCommandEvent>,
+    mut zone_event_writer: EventWriter<ZoneEvent>,
    // mut foliage_command_event_writer: EventWriter<FoliageCommandEvent>,
 ) {
     if key_input.pressed(KeyCode::ControlLeft) || key_input.pressed(KeyCode::ControlRight) {
@@ -27,6 +29,7 @@ pub fn update_commands(
 
             command_event_writer.send(TerrainCommandEvent::SaveAllChunks(true, true, true));
             region_command_event_writer.send(RegionCommandEvent::SaveAll);
+            zone_event_writer.send(ZoneEvent::SaveAllDirtyZones);
            // foliage_command_event_writer.send(FoliageCommandEvent::SaveAll);
         }
     }
@@ -1,6 +1,7 @@
 
  
 use bevy_editor_pls_default_windows::doodads::doodad_manifest::DoodadManifestResource;
+use bevy_editor_pls_default_windows::project::editor_project;
 use crate::AssetLoadState;
 use bevy::prelude::*;
 
@@ -37,7 +38,7 @@ fn load_doodad_manifest(
     asset_server: Res<AssetServer>,
     mut doodad_manifest_resource: ResMut<DoodadManifestResource>,
 ) {
-    doodad_manifest_resource.manifest = Some(asset_server.load("doodad_manifest.doodadmanifest.ron"));
+    doodad_manifest_resource.manifest = Some(asset_server.load(editor_project().doodad_manifest.clone()));
     info!("load doodad manifest");
  
 }
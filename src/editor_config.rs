@@ -1,6 +1,7 @@
 
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use bevy_editor_pls_default_windows::project::editor_project;
 
  
 
@@ -12,7 +13,13 @@ pub struct EditorConfig {
 
 	initial_terrain_to_load: Option<String> ,
 
-	initial_zones_to_load: Option<Vec<String>>
+	initial_zones_to_load: Option<Vec<String>>,
+
+	//undo history for doodad edits and brush strokes, defaults to 256 MB
+	undo_memory_budget_mb: Option<usize>,
+
+	//seconds between recovery snapshots of unsaved edits, 0 disables autosave. defaults to 120
+	autosave_interval_secs: Option<f32>,
 
 }
 
@@ -53,7 +60,7 @@ impl EditorConfig{
 
 	pub fn get_initial_terrain_path_full(&self) -> Option<String> { 
 
-		return self.initial_terrain_to_load.as_ref().map(|t| format!("assets/terrain/{}/terrain_config.ron", t)  )
+		return self.initial_terrain_to_load.as_ref().map(|t| editor_project().terrain_config_path(t).to_string_lossy().to_string()  )
 	}
 
     pub fn get_initial_zones_to_load(&self) -> Option<Vec<String>> { 
@@ -63,6 +70,16 @@ impl EditorConfig{
 		 )
 	}
 
+    pub fn get_undo_memory_budget_bytes(&self) -> Option<usize> {
+
+		return self.undo_memory_budget_mb.map(|mb| mb * 1024 * 1024)
+	}
+
+    pub fn get_autosave_interval_secs(&self) -> Option<f32> {
+
+		return self.autosave_interval_secs
+	}
+
 }
 
 
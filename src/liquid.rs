@@ -9,6 +9,7 @@ use degen_toon_water::toonwater_material::{build_toon_water_material,  ToonWater
 use bevy::{pbr::NotShadowCaster, prelude::*};
 
 use bevy_mod_sysfail::sysfail;
+use bevy_editor_pls_default_windows::project::editor_project;
 use serde::{Deserialize, Serialize};
  
  
@@ -165,11 +166,14 @@ fn load_liquid_manifest(
     mut liquid_manifest_res : ResMut<LiquidManifest> 
 ){
 
- //load the ron file 
+ //load the ron file from the project, so it is not baked into the binary
 
-  let ron_str = include_str!("../assets/liquid_manifest.liquidmanifest.ron");
+  let liquid_manifest_path = editor_project().liquid_manifest_path();
+
+  let ron_str = std::fs::read_to_string(&liquid_manifest_path)
+      .unwrap_or_else(|error| panic!("Unable to read liquid manifest {:?}: {}", liquid_manifest_path, error));
 
     // Parse the .ron string into the Config struct
  
-   *liquid_manifest_res = ron::de::from_str::<LiquidManifest>(ron_str).expect("Failed to parse RON file");
+   *liquid_manifest_res = ron::de::from_str::<LiquidManifest>(&ron_str).expect("Failed to parse RON file");
 }
\ No newline at end of file
@@ -3,6 +3,8 @@ use asset_loading::AssetLoadState;
 use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy_editor_pls_default_windows::lighting::Sun;
 use bevy_editor_pls_default_windows::zones::ZoneEvent;
+use bevy_editor_pls_default_windows::history::EditorHistory;
+use bevy_editor_pls_default_windows::project::editor_project;
 use crate::editor_config::EditorConfig;
 use bevy::core_pipeline::prepass::NormalPrepass;
 use bevy::core_pipeline::prepass::DepthPrepass;
@@ -44,9 +46,12 @@ mod camera;
 mod commands;
 mod editor_pls;
 mod tools;
+mod brush_history;
+mod autosave;
 mod ui;
 mod asset_loading;
 mod liquid;
+mod project;
 
 mod doodads;
 mod terrain;
@@ -58,6 +63,8 @@ use crate::camera::camera_plugin;
 use crate::liquid::liquid_plugin;
 
 use crate::tools::brush_tools_plugin;
+use crate::brush_history::brush_history_plugin;
+use crate::autosave::{autosave_plugin, AutosaveSettings};
 
 use crate::commands::update_commands;
 use crate::ui::editor_ui_plugin;
@@ -75,7 +82,8 @@ fn set_window_icon(
     // here we use the `image` crate to load our icon data from a png file
     // this is not a very bevy-native solution, but it will do
     let (icon_rgba, icon_width, icon_height) = {
-        let image = image::open("assets/images/favicon.png")
+        //the icon belongs to the editor, not to the project being edited
+        let image = image::load_from_memory(include_bytes!("../assets/images/favicon.png"))
             .expect("Failed to open icon path")
             .into_rgba8();
         let (width, height) = image.dimensions();
@@ -96,6 +104,8 @@ fn main() {
     let mut wgpu_settings = WgpuSettings::default();
     wgpu_settings.features |= WgpuFeatures::POLYGON_MODE_LINE;
 
+    let editor_project = project::init_editor_project();
+
     App::new()
         .add_plugins(
             DefaultPlugins
@@ -106,11 +116,19 @@ fn main() {
                         title: "Mesh Terrain Editor".to_string(),
                         ..Default::default()
                     }),
+                    //ui.rs closes the window, after asking about unsaved zones
+                    close_when_requested: false,
                     ..Default::default()
                 })
                 .set(RenderPlugin {
                     render_creation: RenderCreation::Automatic(wgpu_settings),
                     ..default()
+                })
+                .set(AssetPlugin {
+                    file_path: project::absolute_asset_folder(&editor_project)
+                        .to_string_lossy()
+                        .to_string(),
+                    ..default()
                 }),
 
 
@@ -140,6 +158,8 @@ fn main() {
  
         .add_plugins(liquid_plugin)
         .add_plugins(brush_tools_plugin)
+        .add_plugins(brush_history_plugin)
+        .add_plugins(autosave_plugin)
         .add_plugins(editor_ui_plugin)
         .add_plugins(camera_plugin)
           .add_systems(Startup, set_window_icon)
@@ -160,6 +180,9 @@ fn setup(
 
    mut zone_event_writer: EventWriter<ZoneEvent>,
 
+   mut editor_history: ResMut<EditorHistory>,
+   mut autosave_settings: ResMut<AutosaveSettings>,
+
    editor_config: Res<EditorConfigAssets>,
    editor_config_assets: Res<Assets<EditorConfig >>
 
@@ -179,7 +202,13 @@ fn setup(
          
      };
 
-      
+    if let Some(budget_bytes) = editor_config.get_undo_memory_budget_bytes() {
+        editor_history.set_memory_budget(budget_bytes);
+    }
+
+    if let Some(interval_secs) = editor_config.get_autosave_interval_secs() {
+        autosave_settings.interval_secs = interval_secs;
+    }
      
      
     if let Some(terrain_path) = &editor_config.get_initial_terrain_path_full(){
@@ -224,7 +253,7 @@ fn setup(
            transform: Transform::from_xyz(0.0, 40.0, 0.0) , 
             ..default()
         } )
-        .insert(RegionsConfig::load_from_file("assets/regions/regions_config.ron").unwrap())
+        .insert(RegionsConfig::load_from_file(&editor_project().regions_config_path().to_string_lossy()).unwrap())
         .insert(RegionsData::new()) 
         .insert(Visibility::Hidden)  // only in editor 
         ;
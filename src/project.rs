@@ -0,0 +1,103 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use bevy_editor_pls_default_windows::project::{set_editor_project, EditorProject};
+
+/*
+
+Picks the project to edit from the command line:
+
+   main --project path/to/mygame.editorproject.ron
+   main --project path/to/mygame            (a folder, uses the *.editorproject.ron inside if any)
+   main --assets path/to/mygame/assets      (just an asset folder, default layout)
+
+With no arguments the editor looks for a project file in the working directory and otherwise
+falls back to the default `assets` layout.
+
+The working directory is switched to the project root so every relative path, including the
+ones inside bevy_mesh_terrain and bevy_regions, resolves inside the project.
+
+*/
+
+pub fn init_editor_project() -> EditorProject {
+    let args: Vec<String> = env::args().collect();
+
+    let arg_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|index| args.get(index + 1))
+            .map(PathBuf::from)
+    };
+
+    let (project_root, project) = if let Some(project_path) = arg_value("--project") {
+        load_project(&project_path)
+    } else if let Some(asset_folder) = arg_value("--assets") {
+        let project_root = asset_folder
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let project = EditorProject {
+            asset_folder: asset_folder.file_name().map(PathBuf::from).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        (project_root, project)
+    } else {
+        load_project(Path::new("."))
+    };
+
+    if !project_root.as_os_str().is_empty() {
+        env::set_current_dir(&project_root).unwrap_or_else(|error| {
+            panic!("Unable to open project folder {:?}: {}", project_root, error)
+        });
+    }
+
+    //the terrain and regions crates always save their textures under ./assets
+    if project.asset_folder != Path::new("assets") {
+        println!(
+            "warning: asset folder {:?} is not named 'assets', terrain and region textures will still be saved to ./assets",
+            project.asset_folder
+        );
+    }
+
+    println!(
+        "editing project {:?} in {:?}",
+        project.name,
+        env::current_dir().unwrap_or_default()
+    );
+
+    set_editor_project(project.clone()).expect("Editor project was already set");
+
+    project
+}
+
+/// The project asset folder as an absolute path.  Bevy resolves a relative asset path against
+/// BEVY_ASSET_ROOT, CARGO_MANIFEST_DIR or the executable folder, never the working directory.
+pub fn absolute_asset_folder(project: &EditorProject) -> PathBuf {
+    let current_dir = env::current_dir().expect("Unable to read the working directory");
+
+    current_dir.join(&project.asset_folder)
+}
+
+//returns the project root along with the project
+fn load_project(project_path: &Path) -> (PathBuf, EditorProject) {
+    let project_file = match project_path.is_dir() {
+        true => EditorProject::find_project_file(project_path),
+        false => Some(project_path.to_path_buf()),
+    };
+
+    let project_root = match project_path.is_dir() {
+        true => project_path.to_path_buf(),
+        false => project_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    let project = match project_file {
+        Some(project_file) => EditorProject::load_from_file(&project_file).unwrap_or_else(
+            |error| panic!("Unable to load project file {:?}: {}", project_file, error),
+        ),
+        None => EditorProject::default(),
+    };
+
+    (project_root, project)
+}
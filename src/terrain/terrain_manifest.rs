@@ -1,6 +1,7 @@
 use bevy::{asset::ReflectAsset, reflect::TypeRegistry};
 
 use bevy::prelude::*;
+use bevy_editor_pls_default_windows::project::editor_project;
  
 
 use bevy::utils::HashMap;
@@ -35,7 +36,7 @@ fn load_terrain_manifest(
     asset_server: Res<AssetServer>,
     mut terrain_manifest_resource: ResMut<TerrainManifestResource>,
 ) {
-    terrain_manifest_resource.manifest = Some(asset_server.load("terrain_manifest.terrainmanifest.ron"));
+    terrain_manifest_resource.manifest = Some(asset_server.load(editor_project().terrain_manifest.clone()));
 }
   
 
@@ -108,7 +108,7 @@ impl From<EditorToolsState> for EditingTool {
     }
 }
 
-fn update_brush_paint(
+pub(crate) fn update_brush_paint(
     mouse_input: Res<ButtonInput<MouseButton>>, //detect mouse click
 
     cursor_ray: Res<CursorRay>,
@@ -9,14 +9,30 @@ use bevy_mesh_terrain::edit::{BrushType as TerrainBrushType, TerrainCommandEvent
 use bevy_regions::edit::{BrushType as RegionsBrushType, RegionCommandEvent};
 //use bevy_foliage_paint::edit::{BrushType as FoliageBrushType, FoliageCommandEvent};
 
+use bevy_editor_pls_default_windows::zones::{ZoneComponent, ZoneDirty, ZoneEvent, ZoneSaveFailed};
+use bevy::window::WindowCloseRequested;
+
 use std::fmt::{self, Display, Formatter};
 
 use crate::editor_pls::bevy_pls_editor_is_active;
+use crate::autosave::{remove_recovery_files, RecoveryState, UnsavedTerrainEdits};
 
 pub fn editor_ui_plugin(app: &mut App) {
     app.init_resource::<EditorToolsState>()
        // .add_plugins(EguiPlugin)  // only add this if it hasnt been added 
-        .add_systems(Update, editor_tools.run_if(not(bevy_pls_editor_is_active)));
+        .add_systems(Update, editor_tools.run_if(not(bevy_pls_editor_is_active)))
+
+        .init_resource::<UnsavedZonesPromptState>()
+        .add_systems(Update, (handle_window_close_requests, unsaved_zones_prompt).chain());
+}
+
+//the window plugin is set to not close on its own so we can ask about unsaved zones first
+#[derive(Default, Resource)]
+pub struct UnsavedZonesPromptState {
+    pub closing_window: Option<Entity>,
+    pub quit_after_save: bool,
+    //zone name and reason, for the saves that did not go through
+    pub failed_saves: Vec<(String, String)>,
 }
 
 #[derive(Default, Resource, Clone)]
@@ -127,6 +143,7 @@ fn editor_tools(
     mut command_event_writer: EventWriter<TerrainCommandEvent>,
    // mut foliage_command_event_writer: EventWriter<FoliageCommandEvent>,
     mut region_command_event_writer: EventWriter<RegionCommandEvent>,
+    mut zone_event_writer: EventWriter<ZoneEvent>,
 
     mut contexts: EguiContexts,
 
@@ -140,6 +157,7 @@ fn editor_tools(
         if ui.button("Save All   (Ctrl+S)").clicked() {
             command_event_writer.send(TerrainCommandEvent::SaveAllChunks(true, true, true));
             region_command_event_writer.send(RegionCommandEvent::SaveAll );
+            zone_event_writer.send(ZoneEvent::SaveAllDirtyZones);
          //   foliage_command_event_writer.send(FoliageCommandEvent::SaveAll );
         }
 
@@ -342,3 +360,129 @@ fn editor_tools(
         }
     });
 }
+
+
+//recovery files from an earlier session stay until the user has restored or discarded them
+fn remove_recovery_files_if_settled(recovery_state: &RecoveryState) {
+    if !recovery_state.is_busy() {
+        remove_recovery_files();
+    }
+}
+
+fn handle_window_close_requests(
+    mut commands: Commands,
+    mut close_requested_reader: EventReader<WindowCloseRequested>,
+
+    dirty_zone_query: Query<Entity, (With<ZoneComponent>, With<ZoneDirty>)>,
+    unsaved_terrain_edits: Res<UnsavedTerrainEdits>,
+    recovery_state: Res<RecoveryState>,
+
+    mut prompt_state: ResMut<UnsavedZonesPromptState>,
+) {
+    for evt in close_requested_reader.read() {
+        if dirty_zone_query.is_empty() && unsaved_terrain_edits.is_empty() {
+            remove_recovery_files_if_settled(&recovery_state);
+            commands.entity(evt.window).despawn();
+        } else {
+            prompt_state.closing_window = Some(evt.window);
+        }
+    }
+}
+
+fn unsaved_zones_prompt(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+
+    dirty_zone_query: Query<&Name, (With<ZoneComponent>, With<ZoneDirty>)>,
+
+    mut prompt_state: ResMut<UnsavedZonesPromptState>,
+    mut zone_event_writer: EventWriter<ZoneEvent>,
+    mut zone_save_failed_reader: EventReader<ZoneSaveFailed>,
+    mut terrain_command_event_writer: EventWriter<TerrainCommandEvent>,
+    mut region_command_event_writer: EventWriter<RegionCommandEvent>,
+
+    unsaved_terrain_edits: Res<UnsavedTerrainEdits>,
+    recovery_state: Res<RecoveryState>,
+) {
+    let Some(closing_window) = prompt_state.closing_window else {
+        zone_save_failed_reader.clear();
+        return;
+    };
+
+    //stay open so the user can decide what to do with the zones that could not be saved
+    for evt in zone_save_failed_reader.read() {
+        if prompt_state.quit_after_save {
+            prompt_state
+                .failed_saves
+                .push((evt.zone_name.clone(), evt.reason.clone()));
+        }
+    }
+    if !prompt_state.failed_saves.is_empty() {
+        prompt_state.quit_after_save = false;
+    }
+
+    //the saves went through
+    if dirty_zone_query.is_empty() && unsaved_terrain_edits.is_empty() {
+        remove_recovery_files_if_settled(&recovery_state);
+        commands.entity(closing_window).despawn();
+        prompt_state.closing_window = None;
+        return;
+    }
+
+    egui::Window::new("Unsaved Changes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("These have unsaved changes:");
+
+            for zone_name in dirty_zone_query.iter() {
+                ui.label(format!("* zone {}", zone_name));
+            }
+            if !unsaved_terrain_edits.height_chunks.is_empty() {
+                ui.label(format!("* height data for {} chunks", unsaved_terrain_edits.height_chunks.len()));
+            }
+            if !unsaved_terrain_edits.splat_chunks.is_empty() {
+                ui.label(format!("* splat data for {} chunks", unsaved_terrain_edits.splat_chunks.len()));
+            }
+            if unsaved_terrain_edits.regions {
+                ui.label("* region data");
+            }
+
+            if !prompt_state.failed_saves.is_empty() {
+                ui.spacing();
+                ui.colored_label(egui::Color32::RED, "These zones could not be saved:");
+                for (zone_name, reason) in prompt_state.failed_saves.iter() {
+                    ui.colored_label(egui::Color32::RED, format!("* {}: {}", zone_name, reason));
+                }
+            }
+
+            ui.spacing();
+
+            ui.horizontal(|ui| {
+                if ui.button("Save All and Quit").clicked() {
+                    terrain_command_event_writer.send(TerrainCommandEvent::SaveAllChunks(true, true, true));
+                    region_command_event_writer.send(RegionCommandEvent::SaveAll);
+                    zone_event_writer.send(ZoneEvent::SaveAllDirtyZones);
+                    prompt_state.quit_after_save = true;
+                    prompt_state.failed_saves.clear();
+                }
+
+                if ui.button("Quit Without Saving").clicked() {
+                    remove_recovery_files_if_settled(&recovery_state);
+                    commands.entity(closing_window).despawn();
+                    prompt_state.closing_window = None;
+                }
+
+                if ui.button("Cancel").clicked() {
+                    prompt_state.closing_window = None;
+                    prompt_state.quit_after_save = false;
+                    prompt_state.failed_saves.clear();
+                }
+            });
+
+            if prompt_state.quit_after_save {
+                ui.label("Saving...");
+            }
+        });
+}